@@ -0,0 +1,155 @@
+use std::time::Instant;
+
+use super::config::{Easing, RevealAnimation, RevealConfig};
+use super::visibility::Visibility;
+
+/// What's drawn of a bar, `length` along the edge in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Appearance {
+    pub length: f32,
+    pub opacity: f32,
+}
+
+impl Appearance {
+    fn is_visible(self) -> bool {
+        self.length > 0.0 && self.opacity > 0.0
+    }
+}
+
+impl Easing {
+    /// Maps the time passed, from 0 to 1, to how far along the animation is
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+/// Moves a bar's appearance towards what its visibility asks for
+#[derive(Default)]
+pub struct Animation {
+    /// What was asked for last, None until the first frame, which isn't animated
+    target: Option<(Visibility, Appearance)>,
+    /// Where the running animation started, and when
+    from: Option<(Appearance, Instant)>,
+}
+
+impl Animation {
+    /// What to draw at `now` for a bar in `visibility`, with `shown` logical pixels of it out
+    pub fn update(&mut self, config: &RevealConfig, visibility: Visibility, shown: f32, now: Instant) -> Appearance {
+        let current = self.current(config, now);
+
+        let target = match config.animation {
+            // fading out keeps the length it had
+            RevealAnimation::Fade if shown <= 0.0 => Appearance { length: current.length, opacity: 0.0 },
+            _ => Appearance { length: shown, opacity: 1.0 },
+        };
+
+        let Some((previous, old)) = self.target.replace((visibility, target)) else {
+            return target;
+        };
+        if old == target {
+            return current;
+        }
+
+        // a bar peeking out follows the pointer, only getting shown or hidden is animated
+        let animate = config.animation != RevealAnimation::None
+            && !config.duration.is_zero()
+            && (std::mem::discriminant(&previous) != std::mem::discriminant(&visibility)
+                || old.is_visible() != target.is_visible());
+
+        self.from = match (animate, config.animation) {
+            (false, _) => None,
+            // fading in starts out at full length
+            (true, RevealAnimation::Fade) if target.opacity > 0.0 => Some((Appearance { length: target.length, ..current }, now)),
+            (true, _) => Some((current, now)),
+        };
+
+        self.current(config, now)
+    }
+
+    /// Whether frames have to keep coming
+    pub fn is_running(&self) -> bool {
+        self.from.is_some()
+    }
+
+    fn current(&mut self, config: &RevealConfig, now: Instant) -> Appearance {
+        let Some((_, target)) = self.target else {
+            return Appearance { length: 0.0, opacity: 0.0 };
+        };
+        let Some((from, start)) = self.from else {
+            return target;
+        };
+
+        let progress = now.saturating_duration_since(start).as_secs_f32() / config.duration.as_secs_f32();
+        if progress >= 1.0 {
+            self.from = None;
+            return target;
+        }
+
+        let t = config.easing.apply(progress);
+        Appearance {
+            length: from.length + (target.length - from.length) * t,
+            opacity: from.opacity + (target.opacity - from.opacity) * t,
+        }
+    }
+}
+
+#[cfg(test)]
+fn animated(animation: RevealAnimation) -> RevealConfig {
+    use super::config::ConfigState;
+
+    RevealConfig {
+        animation,
+        duration: std::time::Duration::from_millis(100),
+        easing: Easing::Linear,
+        ..ConfigState::default().reveal
+    }
+}
+
+#[test]
+fn slide_out_and_back() {
+    let config = animated(RevealAnimation::Slide);
+    let ms = |ms| std::time::Duration::from_millis(ms);
+    let start = Instant::now();
+    let mut animation = Animation::default();
+
+    // the first frame is drawn as it is
+    assert_eq!(animation.update(&config, Visibility::Hidden, 0.0, start).length, 0.0);
+    assert!(!animation.is_running());
+
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start).length, 0.0);
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(50)).length, 500.0);
+    assert!(animation.is_running());
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(100)).length, 1000.0);
+    assert!(!animation.is_running());
+
+    // turning around halfway goes back from where it is
+    animation.update(&config, Visibility::Hidden, 0.0, start + ms(200));
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(250)).length, 500.0);
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(300)).length, 750.0);
+}
+
+#[test]
+fn fade_keeps_the_length() {
+    let config = animated(RevealAnimation::Fade);
+    let ms = |ms| std::time::Duration::from_millis(ms);
+    let start = Instant::now();
+    let mut animation = Animation::default();
+
+    animation.update(&config, Visibility::Hidden, 0.0, start);
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(50)), Appearance { length: 1000.0, opacity: 0.0 });
+    assert_eq!(animation.update(&config, Visibility::Revealed, 1000.0, start + ms(100)), Appearance { length: 1000.0, opacity: 0.5 });
+
+    animation.update(&config, Visibility::Hidden, 0.0, start + ms(200));
+    assert_eq!(animation.update(&config, Visibility::Hidden, 0.0, start + ms(250)), Appearance { length: 1000.0, opacity: 0.5 });
+
+    // dragging a peeking bar along isn't animated
+    let mut animation = Animation::default();
+    animation.update(&config, Visibility::Peeking { length: 100 }, 100.0, start);
+    assert_eq!(animation.update(&config, Visibility::Peeking { length: 300 }, 300.0, start).length, 300.0);
+}
@@ -1,11 +1,11 @@
-use std::process::Command;
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 use smithay_client_toolkit::{
     delegate_compositor, delegate_layer, delegate_output,
     delegate_pointer, delegate_registry, delegate_seat, delegate_shm, 
 
-    compositor::CompositorHandler, 
+    compositor::{CompositorHandler, CompositorState},
     output::{OutputHandler, OutputState}, 
 
     registry::{ProvidesRegistryState, RegistryState}, 
@@ -15,10 +15,12 @@ use smithay_client_toolkit::{
         Capability, SeatHandler, SeatState, 
         pointer::{PointerEvent, PointerHandler}
     },
-    shell::wlr_layer::{
-        LayerShellHandler, LayerSurface, LayerSurfaceConfigure
+    shell::{
+        WaylandSurface,
+        wlr_layer::{LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
     },
-    shm::{Shm, ShmHandler}
+    shm::{Shm, ShmHandler},
+    reexports::calloop::{LoopHandle, RegistrationToken},
 };
 use wayland_client::{
     protocol::{wl_output, wl_pointer, wl_seat, wl_surface},
@@ -26,79 +28,151 @@ use wayland_client::{
     globals::GlobalList,
 };
 
-use crate::modules::{
-    ClockModule, AudioModule,
-    ModuleInfo,
-};
+use crate::modules::{self, ModuleInfo, Trigger};
 
-use super::config::ConfigState;
+use super::config::{ConfigState, ModuleConfig};
+use super::schedule::schedule_module;
+use super::scale::{ScaleGlobals, SCALE_DENOMINATOR};
 use super::drawing::GraphicsState;
+use super::bar::Bar;
+use super::visibility::Reveal;
+use super::launch::Launcher;
+use super::hot::HotState;
+use super::markup::{Span, parse_or_plain};
 
 pub struct WaylandState {
     pub registry_state: RegistryState,
     pub seat_state: SeatState,
     pub output_state: OutputState,
 
-    pub surface: LayerSurface,
+    pub compositor: CompositorState,
+    pub layer_shell: LayerShell,
+    /// None if the compositor can't do fractional scaling
+    pub scale_globals: Option<ScaleGlobals>,
+
     pub pointer: Option<wl_pointer::WlPointer>,
+
+    /// For redraws requested from event loop callbacks, which don't get one
+    pub qh: QueueHandle<BarWindow>,
 }
 
 pub struct AppState {
-    pub first_configure: bool,
     pub exiting: Arc<RwLock<bool>>,
 
-    pub track_x: bool, 
-    pub track_y: bool,
-    pub no_disappearing: bool,
+    pub modules: Vec<ModuleSlot>,
+    next_module_id: u64,
+    /// Commands started by actions that are still running
+    pub launcher: Launcher,
+
+    pub loop_handle: LoopHandle<'static, BarWindow>,
+}
 
-    pub bar_width: u32,
-    pub modules: Vec<Box<dyn ModuleInfo>>
+/// A running module together with the config it was created from
+pub struct ModuleSlot {
+    pub id: u64,
+    pub timer: Option<RegistrationToken>,
+    pub config: ModuleConfig,
+    pub module: Box<dyn ModuleInfo>,
+    /// What the module displayed last time it was asked
+    pub output: String,
+    /// `output` with its markup parsed
+    pub spans: Vec<Span>,
 }
 
 impl AppState {
-    pub fn new(exiting: Arc<RwLock<bool>>) -> Self {
-        let modules: Vec<Box<dyn ModuleInfo>> = vec![
-            Box::new(ClockModule::new()),
-            Box::new(AudioModule::new()),
-        ];
-
-        Self { 
-            first_configure: true,
+    pub fn new(
+        exiting: Arc<RwLock<bool>>,
+        config: &ConfigState,
+        loop_handle: LoopHandle<'static, BarWindow>,
+    ) -> Self {
+        let mut state = Self { 
             exiting,
 
-            track_x: false,
-            track_y: false,
-            no_disappearing: false,
+            modules: Vec::new(),
+            next_module_id: 0,
+            launcher: Launcher::default(),
 
-            bar_width: 0,
-            modules
+            loop_handle,
+        };
+
+        state.update_modules(config);
+        state
+    }
+
+    /// Rebuilds the module list, modules whose config didn't change are kept running
+    pub fn update_modules(&mut self, config: &ConfigState) {
+        let mut old = std::mem::take(&mut self.modules);
+
+        for module_config in config.modules.iter() {
+            let slot = match old.iter().position(|slot| &slot.config == module_config) {
+                Some(index) => old.remove(index),
+                None => match modules::from_config(module_config) {
+                    Some(mut module) => {
+                        let id = self.next_module_id;
+                        self.next_module_id += 1;
+
+                        let update = module_config.update.unwrap_or(module.update());
+                        let output = module.display();
+
+                        ModuleSlot {
+                            id,
+                            timer: schedule_module(&self.loop_handle, id, update),
+                            config: module_config.clone(),
+                            spans: parse_or_plain(&output, &format!("module `{}`", module_config.name)),
+                            output,
+                            module,
+                        }
+                    }
+                    None => continue,
+                }
+            };
+
+            self.modules.push(slot);
+        }
+
+        for mut slot in old {
+            if let Some(timer) = slot.timer {
+                self.loop_handle.remove(timer);
+            }
+            slot.module.clean_up();
         }
     }
 
     pub fn module_cleanup(&mut self) {
         self.modules.iter_mut()
-            .for_each(|m| m.clean_up());
+            .for_each(|slot| slot.module.clean_up());
     }
 
-    pub fn get_modules_display(&mut self) -> String {
-        self.modules.iter_mut()
-            .map(|m| m.display())
-            .rev()
-            .collect::<Vec<String>>()
-            .join(" ")
+    /// Asks module `id` for its output, returns whether it changed
+    pub fn refresh_module(&mut self, id: u64) -> bool {
+        let Some(slot) = self.modules.iter_mut().find(|slot| slot.id == id) else {
+            return false;
+        };
+
+        let output = slot.module.display();
+        if output == slot.output {
+            return false;
+        }
+
+        slot.spans = parse_or_plain(&output, &format!("module `{}`", slot.config.name));
+        slot.output = output;
+
+        true
     }
 
-    pub fn execute_command(&self, command: &str) {
-        let mut argv = command.split(' ');
-        let mut command = Command::new(argv.nth(0).unwrap());
-        let argv = argv.skip(0);
-        command.args(argv).output().unwrap();
+    /// Lets the module named `name` handle `trigger` itself, returns whether it did
+    pub fn module_input(&mut self, name: &str, trigger: Trigger) -> bool {
+        self.modules.iter_mut()
+            .find(|slot| slot.config.name == name)
+            .is_some_and(|slot| slot.module.handle(trigger))
     }
 }
 
 pub struct BarWindow {
     pub wayland: WaylandState,
     pub graphics: GraphicsState,
+    /// One for every output that gets a bar
+    pub bars: Vec<Bar>,
 
     pub state: AppState,
     pub config: ConfigState
@@ -106,26 +180,137 @@ pub struct BarWindow {
 
 impl BarWindow {
     pub fn new(
-        width: u32, height: u32,
+        config: ConfigState,
         globals: &GlobalList, 
         qh: &QueueHandle<Self>,
-        surface: LayerSurface,
+        loop_handle: LoopHandle<'static, Self>,
         exiting: Arc<RwLock<bool>>,
     ) -> Self {
-        Self {
+        let mut graphics = GraphicsState::new(globals, qh);
+        graphics.fonts.load(&config.font);
+
+        let mut window = Self {
             wayland: WaylandState { 
                 registry_state: RegistryState::new(globals), 
                 seat_state: SeatState::new(globals, qh), 
                 output_state: OutputState::new(globals, qh), 
-                surface, 
-                pointer: None
+                compositor: CompositorState::bind(globals, qh).expect("wl_compositor not available"),
+                layer_shell: LayerShell::bind(globals, qh).expect("zwlr_layer_shell_v1 not available"),
+                scale_globals: ScaleGlobals::bind(globals, qh),
+                pointer: None,
+                qh: qh.clone(),
             },
-            graphics: GraphicsState::new(width, height, globals, qh),
-            state: AppState::new(exiting),
-            config: ConfigState::new()
+            graphics,
+            bars: Vec::new(),
+            state: AppState::new(exiting, &config, loop_handle),
+            config
+        };
+
+        window.load_span_fonts();
+        window
+    }
+
+    /// Loads the fonts the modules' output and the separators ask for, ahead of drawing
+    fn load_span_fonts(&mut self) {
+        let modules = self.state.modules.iter().map(|slot| slot.spans.as_slice());
+        self.graphics.fonts.load_span_fonts(&self.config, modules);
+    }
+
+    /// Re-reads the config file, a config with errors is reported and ignored
+    pub fn reload_config(&mut self) {
+        let (config, report) = ConfigState::load(&ConfigState::default_path());
+
+        if !report.is_empty() {
+            eprint!("{report}");
+        }
+        if report.has_errors() {
+            eprintln!("Keeping the previous config");
+            return;
+        }
+
+        self.apply_config(config);
+    }
+
+    pub fn apply_config(&mut self, config: ConfigState) {
+        if config == self.config {
+            return;
+        }
+
+        if config.bar != self.config.bar {
+            self.bars.iter().for_each(|bar| bar.apply_geometry(&config.bar));
+        }
+
+        let reveal_changed = config.reveal != self.config.reveal;
+        let hot_changed = config.hot != self.config.hot;
+
+        self.graphics.fonts.load(&config.font);
+        self.state.update_modules(&config);
+        self.config = config;
+        self.load_span_fonts();
+
+        // start over, the timeouts may not apply anymore
+        if reveal_changed {
+            for index in 0..self.bars.len() {
+                self.bars[index].reveal = Reveal::new(Instant::now());
+                self.reveal_changed(index);
+            }
+        }
+        if hot_changed {
+            self.bars.iter_mut().for_each(|bar| bar.hot = HotState::default());
         }
+
+        self.sync_bars();
+        self.request_redraw();
+    }
+
+    /// Creates bars for outputs that should have one and drops the rest
+    pub fn sync_bars(&mut self) {
+        let output_state = &self.wayland.output_state;
+        let names = &self.config.bar.outputs;
+
+        let wanted: Vec<wl_output::WlOutput> = output_state.outputs()
+            .filter(|output| {
+                let Some(info) = output_state.info(output) else { return false };
+                names.is_empty() || info.name.as_ref().is_some_and(|name| names.contains(name))
+            })
+            .collect();
+
+        self.bars.retain(|bar| wanted.contains(&bar.output));
+
+        for output in wanted {
+            if !self.bars.iter().any(|bar| bar.output == output) {
+                let bar = Bar::new(&self.wayland, &self.wayland.qh, output, &self.config.bar);
+                self.bars.push(bar);
+            }
+        }
+    }
+
+    pub fn bar_index(&self, surface: &wl_surface::WlSurface) -> Option<usize> {
+        self.bars.iter().position(|bar| bar.surface.wl_surface() == surface)
     }
 
+    /// Refreshes module `id`, returns false if there's no such module anymore
+    pub fn refresh_module(&mut self, id: u64) -> bool {
+        if !self.state.modules.iter().any(|slot| slot.id == id) {
+            return false;
+        }
+
+        if !self.state.refresh_module(id) {
+            return true;
+        }
+        self.load_span_fonts();
+
+        let reveal = self.state.modules.iter().find(|slot| slot.id == id).and_then(|slot| slot.config.reveal);
+
+        match reveal {
+            Some(duration) => for index in 0..self.bars.len() {
+                self.bars[index].reveal.show_for(&self.config.reveal, duration, Instant::now());
+                self.reveal_changed(index);
+            }
+            None => self.request_redraw(),
+        }
+        true
+    }
 }
 
 impl PointerHandler for BarWindow {
@@ -145,9 +330,14 @@ impl CompositorHandler for BarWindow {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
-        _new_factor: i32,
-    ) {}
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
+    ) {
+        // wp_fractional_scale_v1 is more precise when it's there
+        if self.wayland.scale_globals.is_none() {
+            self.set_scale(surface, new_factor as u32 * SCALE_DENOMINATOR);
+        }
+    }
 
     fn transform_changed(
         &mut self,
@@ -161,10 +351,15 @@ impl CompositorHandler for BarWindow {
         &mut self,
         _: &Connection,
         qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
+        surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
-        self.draw(qh);
+        let Some(index) = self.bar_index(surface) else { return };
+        self.bars[index].frame_pending = false;
+
+        if self.bars[index].dirty {
+            self.draw(index, qh);
+        }
     }
 
     fn surface_enter(
@@ -185,24 +380,39 @@ impl CompositorHandler for BarWindow {
 }
 
 impl LayerShellHandler for BarWindow {
-    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
-        *self.state.exiting.write().unwrap() = true;
+    /// The compositor may close a bar whose output is going away, the others keep running
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, surface: &LayerSurface) {
+        if let Some(index) = self.bar_index(surface.wl_surface()) {
+            self.bars.remove(index);
+        }
     }
 
     fn configure(
             &mut self,
             _: &Connection,
             qh: &QueueHandle<Self>,
-            _: &LayerSurface,
+            surface: &LayerSurface,
             configure: LayerSurfaceConfigure,
             _: u32,
     ) {
-        self.graphics.width = configure.new_size.0;
-        self.graphics.height = configure.new_size.1;
+        let Some(index) = self.bar_index(surface.wl_surface()) else { return };
+        let bar = &mut self.bars[index];
 
-        if self.state.first_configure {
-            self.draw(qh);
-            self.state.first_configure = false;
+        let resized = (bar.width, bar.height) != configure.new_size;
+
+        if resized {
+            bar.invalidate();
+        }
+
+        bar.width = configure.new_size.0;
+        bar.height = configure.new_size.1;
+
+        if bar.first_configure {
+            bar.first_configure = false;
+            self.draw(index, qh);
+        }
+        else if resized {
+            self.request_bar_redraw(index);
         }
     }
 }
@@ -217,21 +427,28 @@ impl OutputHandler for BarWindow {
         _: &Connection,
         _: &QueueHandle<Self>,
         _: wl_output::WlOutput,
-    ) {}
+    ) {
+        self.sync_bars();
+    }
 
+    /// Outputs are filtered by name, which can arrive after the output itself
     fn update_output(
         &mut self,
         _: &Connection,
         _: &QueueHandle<Self>,
         _: wl_output::WlOutput,
-    ) {}
+    ) {
+        self.sync_bars();
+    }
 
     fn output_destroyed(
         &mut self,
         _: &Connection,
         _: &QueueHandle<Self>,
-        _: wl_output::WlOutput,
-    ) {}
+        output: wl_output::WlOutput,
+    ) {
+        self.bars.retain(|bar| bar.output != output);
+    }
 }
 
 impl SeatHandler for BarWindow {
@@ -0,0 +1,158 @@
+use std::time::Instant;
+
+use smithay_client_toolkit::{
+    reexports::calloop::RegistrationToken,
+    shell::{
+        WaylandSurface,
+        wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerSurface},
+    },
+    shm::slot::Buffer,
+};
+use wayland_client::{QueueHandle, protocol::wl_output::WlOutput};
+
+use super::{BarWindow, WaylandState};
+use super::config::{self, BarConfig, Edge};
+use super::scale::{FractionalScale, SCALE_DENOMINATOR, to_physical};
+use super::animation::Animation;
+use super::hot::HotState;
+use super::render::Hitboxes;
+use super::visibility::Reveal;
+
+/// The bar on one output
+pub struct Bar {
+    pub output: WlOutput,
+    pub fractional: Option<FractionalScale>,
+    pub surface: LayerSurface,
+
+    /// Logical size, as configured by the compositor
+    pub width: u32,
+    pub height: u32,
+    /// Preferred scale in 120ths
+    pub scale: u32,
+
+    pub first_configure: bool,
+    pub buffer: Option<Buffer>,
+    /// Something changed since the last committed frame
+    pub dirty: bool,
+    /// A frame callback was requested and hasn't fired yet
+    pub frame_pending: bool,
+    /// Copy of the last committed frame, to damage only what changed
+    pub previous: Vec<u8>,
+
+    pub reveal: Reveal,
+    /// Eases between what `reveal` asked for before and now
+    pub animation: Animation,
+    /// Fires when `reveal` times out
+    pub reveal_timer: Option<RegistrationToken>,
+    pub hot: HotState,
+    /// Fires when the pointer dwelled in a hot region long enough
+    pub hot_timer: Option<RegistrationToken>,
+    /// Where modules and spans with an `action` were in the last frame, in buffer pixels
+    pub hitboxes: Hitboxes,
+    /// Smooth scrolling that didn't add up to a whole step yet
+    pub scroll: f64,
+}
+
+impl Bar {
+    pub fn new(
+        wayland: &WaylandState,
+        qh: &QueueHandle<BarWindow>,
+        output: WlOutput,
+        config: &BarConfig,
+    ) -> Self {
+        let surface = wayland.compositor.create_surface(qh);
+        let fractional = wayland.scale_globals.as_ref()
+            .map(|globals| globals.create(&surface, qh));
+
+        let surface = wayland.layer_shell.create_layer_surface(
+            qh, surface, layer(config.layer), Some("svbar"), Some(&output)
+        );
+        surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+
+        let bar = Self {
+            output, fractional, surface,
+
+            width: 0,
+            height: 0,
+            scale: SCALE_DENOMINATOR,
+
+            first_configure: true,
+            buffer: None,
+            dirty: true,
+            frame_pending: false,
+            previous: Vec::new(),
+
+            reveal: Reveal::new(Instant::now()),
+            animation: Animation::default(),
+            reveal_timer: None,
+            hot: HotState::default(),
+            hot_timer: None,
+            hitboxes: Hitboxes::default(),
+            scroll: 0.0,
+        };
+
+        bar.apply_geometry(config);
+        bar
+    }
+
+    /// Without a configured width the bar is stretched along the whole edge.
+    /// Takes effect once the compositor configures the surface again.
+    pub fn apply_geometry(&self, config: &BarConfig) {
+        let length = config.width.unwrap_or(0);
+
+        let (anchor, width, height) = match config.edge {
+            Edge::Top    => (Anchor::TOP | Anchor::LEFT | Anchor::RIGHT, length, config.height),
+            Edge::Bottom => (Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT, length, config.height),
+            Edge::Left   => (Anchor::LEFT | Anchor::TOP | Anchor::BOTTOM, config.height, length),
+            Edge::Right  => (Anchor::RIGHT | Anchor::TOP | Anchor::BOTTOM, config.height, length),
+        };
+
+        let margins = config.margins;
+
+        self.surface.set_anchor(anchor);
+        self.surface.set_size(width, height);
+        self.surface.set_layer(layer(config.layer));
+        self.surface.set_margin(margins.top, margins.right, margins.bottom, margins.left);
+        // 0 still keeps the bar clear of other panels' zones, it just doesn't reserve one
+        self.surface.set_exclusive_zone(if config.exclusive { config.height as i32 } else { 0 });
+        self.surface.commit();
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale as f32 / SCALE_DENOMINATOR as f32
+    }
+
+    /// Size of the buffers, which are drawn at the output's scale
+    pub fn physical_size(&self) -> (u32, u32) {
+        (to_physical(self.width, self.scale), to_physical(self.height, self.scale))
+    }
+
+    /// Turns a surface position into (along the edge, away from the edge),
+    /// so the reveal gesture works the same on every edge
+    pub fn edge_position(&self, edge: Edge, (x, y): (f64, f64)) -> (f64, f64) {
+        // the pointer stops somewhere in the last pixel, not at its far side
+        let far = |size: u32, position: f64| size as f64 - 1.0 - position;
+
+        match edge {
+            Edge::Top    => (x, y),
+            Edge::Bottom => (x, far(self.height, y)),
+            Edge::Left   => (y, x),
+            Edge::Right  => (y, far(self.width, x)),
+        }
+    }
+
+    /// Forgets the current buffer after the size or scale changed
+    pub fn invalidate(&mut self) {
+        self.buffer = None;
+        self.previous.clear();
+    }
+}
+
+fn layer(layer: config::Layer) -> Layer {
+    match layer {
+        config::Layer::Background => Layer::Background,
+        config::Layer::Bottom => Layer::Bottom,
+        config::Layer::Top => Layer::Top,
+        config::Layer::Overlay => Layer::Overlay,
+    }
+}
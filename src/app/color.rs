@@ -0,0 +1,198 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+const NAMED: &[(&str, Color)] = &[
+    ("transparent", Color::rgba(0, 0, 0, 0)),
+    ("black",   Color::rgb(0x00, 0x00, 0x00)),
+    ("white",   Color::rgb(0xff, 0xff, 0xff)),
+    ("gray",    Color::rgb(0x80, 0x80, 0x80)),
+    ("grey",    Color::rgb(0x80, 0x80, 0x80)),
+    ("silver",  Color::rgb(0xc0, 0xc0, 0xc0)),
+    ("red",     Color::rgb(0xff, 0x00, 0x00)),
+    ("maroon",  Color::rgb(0x80, 0x00, 0x00)),
+    ("orange",  Color::rgb(0xff, 0xa5, 0x00)),
+    ("yellow",  Color::rgb(0xff, 0xff, 0x00)),
+    ("olive",   Color::rgb(0x80, 0x80, 0x00)),
+    ("lime",    Color::rgb(0x00, 0xff, 0x00)),
+    ("green",   Color::rgb(0x00, 0x80, 0x00)),
+    ("teal",    Color::rgb(0x00, 0x80, 0x80)),
+    ("cyan",    Color::rgb(0x00, 0xff, 0xff)),
+    ("aqua",    Color::rgb(0x00, 0xff, 0xff)),
+    ("blue",    Color::rgb(0x00, 0x00, 0xff)),
+    ("navy",    Color::rgb(0x00, 0x00, 0x80)),
+    ("purple",  Color::rgb(0x80, 0x00, 0x80)),
+    ("magenta", Color::rgb(0xff, 0x00, 0xff)),
+    ("fuchsia", Color::rgb(0xff, 0x00, 0xff)),
+    ("pink",    Color::rgb(0xff, 0xc0, 0xcb)),
+    ("brown",   Color::rgb(0xa5, 0x2a, 0x2a)),
+];
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xff }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Accepts `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa` (the `#` is optional),
+    /// `rgb(r, g, b)`, `rgba(r, g, b, a)` with `a` in 0..=1, and the names in `NAMED`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let lower = text.to_ascii_lowercase();
+
+        if let Some((_, color)) = NAMED.iter().find(|(name, _)| *name == lower) {
+            return Ok(*color);
+        }
+
+        if let Some(args) = lower.strip_prefix("rgba(").and_then(|s| s.strip_suffix(')')) {
+            return Self::from_function(args, true).map_err(|why| format!("invalid color `{text}`: {why}"));
+        }
+        if let Some(args) = lower.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+            return Self::from_function(args, false).map_err(|why| format!("invalid color `{text}`: {why}"));
+        }
+
+        Self::from_hex(text).ok_or_else(|| format!(
+            "invalid color `{text}`, expected `#rrggbb`, `#rrggbbaa`, `#rgb`, `rgb()`, `rgba()` or a color name"
+        ))
+    }
+
+    fn from_hex(text: &str) -> Option<Self> {
+        let digits = text.strip_prefix('#').unwrap_or(text);
+
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let nibble = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap() * 0x11;
+        let byte = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+
+        match digits.len() {
+            3 => Some(Self::rgb(nibble(0), nibble(1), nibble(2))),
+            4 => Some(Self::rgba(nibble(0), nibble(1), nibble(2), nibble(3))),
+            6 => Some(Self::rgb(byte(0), byte(2), byte(4))),
+            8 => Some(Self::rgba(byte(0), byte(2), byte(4), byte(6))),
+            _ => None,
+        }
+    }
+
+    fn from_function(args: &str, alpha: bool) -> Result<Self, String> {
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let expected = if alpha { 4 } else { 3 };
+
+        if args.len() != expected {
+            return Err(format!("expected {expected} arguments, found {}", args.len()));
+        }
+
+        let channel = |arg: &str| arg.parse::<u8>()
+            .map_err(|_| format!("`{arg}` is not a channel value between 0 and 255"));
+
+        let a = match alpha {
+            false => 0xff,
+            true => match args[3].parse::<f32>() {
+                Ok(a) if (0.0..=1.0).contains(&a) => (a * 255.0).round() as u8,
+                _ => return Err(format!("`{}` is not an alpha value between 0 and 1", args[3])),
+            }
+        };
+
+        Ok(Self::rgba(channel(args[0])?, channel(args[1])?, channel(args[2])?, a))
+    }
+
+    /// Pixel in `wl_shm::Format::Argb8888`, which is premultiplied
+    pub fn as_argb(&self) -> u32 {
+        let premultiply = |c: u8| (c as u32 * self.a as u32 + 127) / 255;
+
+        (self.a as u32) << 24
+            | premultiply(self.r) << 16
+            | premultiply(self.g) << 8
+            | premultiply(self.b)
+    }
+
+    /// Draws this color with `coverage` (0 to 1) over `dst`, a premultiplied Argb8888 pixel.
+    /// `linear` blends in linear light instead of on the sRGB values, which keeps
+    /// light text on dark backgrounds from looking thinner than dark text on light ones.
+    pub fn blend_over(&self, dst: u32, coverage: f32, linear: bool) -> u32 {
+        let alpha = self.a as f64 / 255.0 * coverage.clamp(0.0, 1.0) as f64;
+        let dst_alpha = (dst >> 24) as f64 / 255.0;
+        let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+
+        let channel = |src: u8, shift: u32| -> u32 {
+            let src = src as f64 / 255.0;
+            let dst = ((dst >> shift) & 0xff) as f64 / 255.0;
+
+            let out = match linear {
+                false => src * alpha + dst * (1.0 - alpha),
+                true => {
+                    // premultiplied values have to be straightened before they can be converted
+                    let dst = if dst_alpha > 0.0 { to_linear(dst / dst_alpha) * dst_alpha } else { 0.0 };
+                    let out = to_linear(src) * alpha + dst * (1.0 - alpha);
+
+                    if out_alpha > 0.0 { to_srgb(out / out_alpha) * out_alpha } else { 0.0 }
+                }
+            };
+
+            (out.clamp(0.0, 1.0) * 255.0).round() as u32
+        };
+
+        ((out_alpha * 255.0).round() as u32) << 24
+            | channel(self.r, 16) << 16
+            | channel(self.g, 8) << 8
+            | channel(self.b, 0)
+    }
+}
+
+fn to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn to_srgb(c: f64) -> f64 {
+    match c {
+        c if c <= 0.0031308 => c * 12.92,
+        // the curve lands a hair below 1 at 1, which would round full white down
+        c if c >= 1.0 => 1.0,
+        c => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+#[test]
+fn parse_notations() {
+    assert_eq!(Color::parse("#102030"), Ok(Color::rgb(0x10, 0x20, 0x30)));
+    assert_eq!(Color::parse("102030"), Ok(Color::rgb(0x10, 0x20, 0x30)));
+    assert_eq!(Color::parse("#10203080"), Ok(Color::rgba(0x10, 0x20, 0x30, 0x80)));
+    assert_eq!(Color::parse("#f0a"), Ok(Color::rgb(0xff, 0x00, 0xaa)));
+    assert_eq!(Color::parse("rgb(1, 2, 3)"), Ok(Color::rgb(1, 2, 3)));
+    assert_eq!(Color::parse("RGBA(1,2,3,0.5)"), Ok(Color::rgba(1, 2, 3, 128)));
+    assert_eq!(Color::parse("Red"), Ok(Color::rgb(0xff, 0, 0)));
+
+    assert!(Color::parse("#ä0000").is_err());
+    assert!(Color::parse("#12345").is_err());
+    assert!(Color::parse("rgb(256, 0, 0)").is_err());
+    assert!(Color::parse("rgba(0, 0, 0, 2)").is_err());
+}
+
+#[test]
+fn premultiplied_argb() {
+    assert_eq!(Color::rgb(0x10, 0x20, 0x30).as_argb(), 0xff102030);
+    assert_eq!(Color::rgba(0xff, 0x80, 0x00, 0x80).as_argb(), 0x80804000);
+    assert_eq!(Color::parse("transparent").unwrap().as_argb(), 0);
+}
+
+#[test]
+fn blend_premultiplied() {
+    let white = Color::rgb(0xff, 0xff, 0xff);
+
+    assert_eq!(white.blend_over(0xff000000, 0.5, false), 0xff808080);
+    assert_eq!(white.blend_over(0x00000000, 0.5, false), 0x80808080);
+    assert_eq!(white.blend_over(0xff102030, 0.0, false), 0xff102030);
+    assert_eq!(Color::rgba(0xff, 0, 0, 0x80).blend_over(0xff0000ff, 1.0, false), 0xff80007f);
+
+    // half the light is brighter than half the sRGB value
+    assert_eq!(white.blend_over(0xff000000, 0.5, true), 0xffbcbcbc);
+    assert_eq!(white.blend_over(0x00000000, 0.5, true), 0x80808080);
+}
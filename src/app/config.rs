@@ -1,73 +1,1129 @@
 use std::fs;
+use std::time::Duration;
 
-#[derive(Debug)]
-pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8
+use crate::modules::{OPTIONS, OptionType, TRIGGERS, Trigger, Update};
+
+use super::color::Color;
+use super::markup;
+use super::diagnostic::{Diagnostic, Report};
+use super::syntax::{self, Document, Entry, Section, Value};
+
+/// Screen edge the bar is attached to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// Left and right bars run top to bottom
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Edge::Left | Edge::Right)
+    }
+}
+
+/// Layer shell layer, from the bottom to the top of the stack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    Background,
+    Bottom,
+    Top,
+    Overlay,
+}
+
+/// Distance from the anchored edges, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margins {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+/// `[bar]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarConfig {
+    /// Length along the edge, None stretches the bar over the whole output
+    pub width: Option<u32>,
+    /// Thickness away from the edge
+    pub height: u32,
+    /// Names of the outputs that get a bar, like `DP-1`, empty for all of them
+    pub outputs: Vec<String>,
+    pub edge: Edge,
+    pub layer: Layer,
+    pub margins: Margins,
+    /// Always shown in full, with its space reserved so windows aren't placed under it
+    pub exclusive: bool,
+    /// Vertical bars show everything on one line turned by 90°, instead of a module per line
+    pub rotate_text: bool,
+    pub background: Color,
+    pub foreground: Color,
+}
+
+/// `[font]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontConfig {
+    /// Family names or font file paths, later ones are used for characters the earlier ones lack
+    pub families: Vec<String>,
+    /// In pixels
+    pub size: f32,
+    /// Blend text in linear light, so thin light text on a dark bar doesn't look starved
+    pub gamma_correct: bool,
+}
+
+/// How a bar that isn't exclusive is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RevealMode {
+    /// Never hidden
+    Always,
+    /// Shown when the pointer rests on it
+    Hover,
+    /// Pulled out by pushing the pointer into the corner and along the edge
+    EdgeDrag,
+}
+
+/// How showing and hiding the bar is animated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RevealAnimation {
+    None,
+    /// Grows out of the corner along the edge
+    Slide,
+    Fade,
 }
 
-impl Color {
-    pub fn from_hex(hex: String) -> Self {
-        let r = u8::from_str_radix(&hex[0..2], 16).expect("Invalid red channel value");
-        let g = u8::from_str_radix(&hex[2..4], 16).expect("Invalid green channel value");
-        let b = u8::from_str_radix(&hex[4..6], 16).expect("Invalid blue channel value");
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+/// `[reveal]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevealConfig {
+    pub mode: RevealMode,
+    /// How long the pointer rests before the whole bar is shown, in hover mode
+    pub delay: Duration,
+    /// How long the bar stays after the pointer left
+    pub hide_delay: Duration,
+    /// How long the bar stays at least once it's shown in full
+    pub min_visible: Duration,
+    pub animation: RevealAnimation,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+/// Something the bar does when asked to, like from a hot corner
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Command(String),
+    /// `@toggle-pin`, keeps the bar shown or lets it hide again
+    TogglePin,
+    /// `@reload`, reads the config file again
+    Reload,
+    /// `@refresh`, asks the module bound to for its output right away, or every module
+    Refresh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Part of the bar's surface the pointer can be pushed into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HotRegion {
+    Corner(Corner),
+    /// A side of the bar, from `from` to `to` as fractions of its length
+    Edge { edge: Edge, from: f32, to: f32 },
+}
 
-        Self { r, g, b }
+/// `[hot.<name>]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotConfig {
+    pub name: String,
+    pub region: HotRegion,
+    /// How deep the region reaches into the bar, in logical pixels
+    pub size: u32,
+    /// How long the pointer stays before the action runs
+    pub dwell: Duration,
+    /// How long after running the action coming back doesn't run it again
+    pub cooldown: Duration,
+    pub action: Action,
+}
+
+impl HotConfig {
+    fn new(name: &str, region: HotRegion, action: Action) -> Self {
+        Self { name: name.to_string(), region, size: 1, dwell: Duration::ZERO, cooldown: Duration::from_millis(500), action }
     }
+}
+
+/// `[zone.left]`, `[zone.center]` or `[zone.right]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneConfig {
+    /// Names of the modules shown, from left to right
+    pub modules: Vec<String>,
+    /// Logical pixels between modules and separators
+    pub spacing: f32,
+    /// Drawn between modules, empty for none
+    pub separator: String,
+    /// When the zones don't fit, the one with the lowest priority is cut short first
+    pub priority: i64,
+}
 
-    /// (0xFF >> 24) + (r >> 16) + (g >> 8) + b
-    pub fn as_hex(&self) -> i32 {
-        let r = self.r as u64;
-        let g = self.g as u64;
-        let b = self.b as u64;
+impl ZoneConfig {
+    fn new(priority: i64) -> Self {
+        Self { modules: Vec::new(), spacing: 8.0, separator: String::new(), priority }
+    }
+}
 
-        let r = r << 16;
-        let g = g << 8;
+/// Names of the zones, in the order of `ConfigState::zones`
+pub const ZONES: [&str; 3] = ["left", "center", "right"];
 
-        (r + g + b) as i32
+/// A module listed in one of the zones, configured by its `[module.<name>]` table.
+/// `type` defaults to the name so `[module.clock]` is enough for the builtin clock,
+/// while `[module.utc]` with `type = "clock"` adds a second one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleConfig {
+    pub name: String,
+    pub kind: String,
+    /// `interval`, overrides how often the module wants to be refreshed
+    pub update: Option<Update>,
+    /// `reveal`, how long the bar is shown whenever the module's output changes
+    pub reveal: Option<Duration>,
+    /// `on_click` and the like
+    pub bindings: Vec<(Trigger, Action)>,
+    pub options: Vec<(String, Value)>,
+}
+
+impl ModuleConfig {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), kind: name.to_string(), update: None, reveal: None, bindings: Vec::new(), options: Vec::new() }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.options.iter().rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn get_float(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(Value::as_float)
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(Value::as_int)
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConfigState {
-    pub bar_color:  Color,
-    pub text_color: Color
+    pub bar: BarConfig,
+    pub font: FontConfig,
+    pub reveal: RevealConfig,
+    /// Hot corners and edges, `[input] overview_command` is the one called `overview`
+    pub hot: Vec<HotConfig>,
+    /// Left, center and right
+    pub zones: [ZoneConfig; 3],
+    /// Every module listed in a zone, once
+    pub modules: Vec<ModuleConfig>,
 }
 
 impl ConfigState {
+    /// Loads the default config file, printing whatever was wrong with it.
+    /// Broken keys keep their default value.
     pub fn new() -> Self {
-        let mut config = ConfigState::default();
+        let path = Self::default_path();
 
-        let path = {
-            let home = std::env::var("HOME").expect("lmao $HOMEless");
-            format!("{}/.config/svbar/config", home)
+        match fs::exists(&path) {
+            Ok(true) => {}
+            _ => return ConfigState::default()
+        }
+
+        let (config, report) = Self::load(&path);
+        if !report.is_empty() {
+            eprint!("{report}");
+        }
+
+        config
+    }
+
+    pub fn default_path() -> String {
+        let home = std::env::var("HOME").expect("lmao $HOMEless");
+        format!("{}/.config/svbar/config", home)
+    }
+
+    pub fn load(path: &str) -> (Self, Report) {
+        let mut report = Report { path: path.to_string(), ..Report::default() };
+
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(why) => {
+                report.diagnostics.push(Diagnostic::error(0, 0, format!("failed to read config: {why}")));
+                return (ConfigState::default(), report);
+            }
+        };
+
+        let (config, diagnostics) = Self::from_source(&source);
+        report.source = source;
+        report.diagnostics = diagnostics;
+
+        (config, report)
+    }
+
+    pub fn from_source(source: &str) -> (Self, Vec<Diagnostic>) {
+        let (document, mut diagnostics) = syntax::parse(source);
+
+        let mut loader = Loader {
+            config: ConfigState::default(),
+            diagnostics: Vec::new(),
+            listed_at: Vec::new(),
         };
+        loader.load(&document);
+        diagnostics.append(&mut loader.diagnostics);
+
+        (loader.config, diagnostics)
+    }
+}
 
-        if let Ok(exists) = fs::exists(&path) {
-            if !exists { return config; }
+const BAR_KEYS: &[&str] = &[
+    "width", "height", "outputs", "edge", "layer", "margin", "exclusive", "rotate_text",
+    "background", "foreground",
+];
+const FONT_KEYS: &[&str] = &["family", "path", "size", "gamma_correct"];
+const REVEAL_KEYS: &[&str] = &["mode", "delay", "hide_delay", "min_visible", "animation", "duration", "easing"];
+const INPUT_KEYS: &[&str] = &["overview_command"];
+const HOT_KEYS: &[&str] = &["region", "range", "size", "dwell", "cooldown", "action"];
+const MODULES_KEYS: &[&str] = &["order"];
+const ZONE_KEYS: &[&str] = &["modules", "spacing", "separator", "priority"];
+
+struct Loader {
+    config: ConfigState,
+    diagnostics: Vec<Diagnostic>,
+    /// Where each module was listed first, for errors about modules without a table
+    listed_at: Vec<(String, (usize, usize))>,
+}
+
+impl Loader {
+    fn load(&mut self, document: &Document) {
+        // configured hot corners replace the default one
+        if document.sections.iter().any(|section| section.name.starts_with("hot.")) {
+            self.config.hot.clear();
         }
-        else { return config; }
 
-        if let Some(contents) = fs::read_to_string(path).ok() {
-            for line in contents.lines() {
-                if let Some(hex) = line.strip_prefix("background=") {
-                    config.bar_color = Color::from_hex(hex.to_string());
+        for section in document.sections.iter() {
+            let known = match section.name.as_str() {
+                // keys before any header are the old `background=`/`foreground=` format
+                "" | "bar" => { self.load_bar(section); BAR_KEYS }
+                "font" => { self.load_font(section); FONT_KEYS }
+                "reveal" => { self.load_reveal(section); REVEAL_KEYS }
+                "input" => { self.load_input(section); INPUT_KEYS }
+                "modules" => { self.load_order(section); MODULES_KEYS }
+                name if name.starts_with("zone.") => match ZONES.iter().position(|z| name == format!("zone.{z}")) {
+                    Some(zone) => { self.load_zone(section, zone); ZONE_KEYS }
+                    None => {
+                        self.diagnostics.push(Diagnostic::warning(section.line, 1, format!(
+                            "unknown zone `[{name}]`, expected one of: {}", ZONES.map(|z| format!("zone.{z}")).join(", ")
+                        )));
+                        continue;
+                    }
                 }
-                else if let Some(hex) = line.strip_prefix("foreground=") {
-                    config.text_color = Color::from_hex(hex.to_string());
+                name if name.starts_with("hot.") => { self.load_hot(section, &name["hot.".len()..]); HOT_KEYS }
+                name if name.starts_with("module.") => continue,
+                name => {
+                    self.diagnostics.push(Diagnostic::warning(
+                        section.line, 1, format!("unknown section `[{name}]`")
+                    ));
+                    continue;
                 }
+            };
+
+            for entry in section.entries.iter().filter(|e| !known.contains(&e.key.as_str())) {
+                self.diagnostics.push(Diagnostic::warning(
+                    entry.line, entry.column, format!("unknown key `{}`", entry.key)
+                ));
             }
         }
 
-        config
+        self.collect_modules();
+        self.load_modules(document);
+    }
+
+    fn load_bar(&mut self, section: &Section) {
+        let bar = &mut self.config.bar;
+
+        if let Some(width) = check(&mut self.diagnostics, section, "width", positive) {
+            bar.width = Some(width);
+        }
+        if let Some(height) = check(&mut self.diagnostics, section, "height", positive) {
+            bar.height = height;
+        }
+        if let Some(outputs) = check(&mut self.diagnostics, section, "outputs", strings) {
+            bar.outputs = outputs;
+        }
+        if let Some(edge) = check(&mut self.diagnostics, section, "edge", edge) {
+            bar.edge = edge;
+        }
+        if let Some(layer) = check(&mut self.diagnostics, section, "layer", layer) {
+            bar.layer = layer;
+        }
+        if let Some(margins) = check(&mut self.diagnostics, section, "margin", margins) {
+            bar.margins = margins;
+        }
+        if let Some(exclusive) = check(&mut self.diagnostics, section, "exclusive", boolean) {
+            bar.exclusive = exclusive;
+        }
+        if let Some(rotate) = check(&mut self.diagnostics, section, "rotate_text", boolean) {
+            bar.rotate_text = rotate;
+        }
+        if let Some(color) = check(&mut self.diagnostics, section, "background", color) {
+            bar.background = color;
+        }
+        if let Some(color) = check(&mut self.diagnostics, section, "foreground", color) {
+            bar.foreground = color;
+        }
+    }
+
+    fn load_font(&mut self, section: &Section) {
+        let font = &mut self.config.font;
+
+        if let Some((families, size)) = check(&mut self.diagnostics, section, "family", font_list) {
+            font.families = families;
+            font.size = size.unwrap_or(font.size);
+        }
+        // a single font file, the way fonts were configured before `family`
+        if let Some(path) = check(&mut self.diagnostics, section, "path", string) {
+            font.families.insert(0, path);
+        }
+        if let Some(size) = check(&mut self.diagnostics, section, "size", positive_float) {
+            font.size = size as f32;
+        }
+        if let Some(gamma_correct) = check(&mut self.diagnostics, section, "gamma_correct", boolean) {
+            font.gamma_correct = gamma_correct;
+        }
+    }
+
+    fn load_reveal(&mut self, section: &Section) {
+        let reveal = &mut self.config.reveal;
+
+        if let Some(mode) = check(&mut self.diagnostics, section, "mode", reveal_mode) {
+            reveal.mode = mode;
+        }
+        if let Some(delay) = check(&mut self.diagnostics, section, "delay", seconds) {
+            reveal.delay = delay;
+        }
+        if let Some(delay) = check(&mut self.diagnostics, section, "hide_delay", seconds) {
+            reveal.hide_delay = delay;
+        }
+        if let Some(time) = check(&mut self.diagnostics, section, "min_visible", seconds) {
+            reveal.min_visible = time;
+        }
+        if let Some(animation) = check(&mut self.diagnostics, section, "animation", reveal_animation) {
+            reveal.animation = animation;
+        }
+        if let Some(duration) = check(&mut self.diagnostics, section, "duration", seconds) {
+            reveal.duration = duration;
+        }
+        if let Some(easing) = check(&mut self.diagnostics, section, "easing", easing) {
+            reveal.easing = easing;
+        }
+    }
+
+    /// `overview_command` is what the bottom-left hot corner was before it could be configured
+    fn load_input(&mut self, section: &Section) {
+        let Some(command) = check(&mut self.diagnostics, section, "overview_command", string) else { return };
+        let hot = &mut self.config.hot;
+
+        match hot.iter_mut().find(|hot| hot.name == "overview") {
+            _ if command.is_empty() => hot.retain(|hot| hot.name != "overview"),
+            Some(overview) => overview.action = Action::Command(command),
+            None => hot.push(HotConfig::new(
+                "overview", HotRegion::Corner(Corner::BottomLeft), Action::Command(command)
+            )),
+        }
     }
+
+    fn load_hot(&mut self, section: &Section, name: &str) {
+        let Some(action) = check(&mut self.diagnostics, section, "action", action) else {
+            if section.get("action").is_none() {
+                self.diagnostics.push(Diagnostic::error(section.line, 1, format!("`[hot.{name}]` has no `action`")));
+            }
+            return;
+        };
+        let mut hot = HotConfig::new(name, HotRegion::Corner(Corner::BottomLeft), action);
+
+        if let Some(region) = check(&mut self.diagnostics, section, "region", hot_region) {
+            hot.region = region;
+        }
+        if let Some((from, to)) = check(&mut self.diagnostics, section, "range", range) {
+            match &mut hot.region {
+                HotRegion::Edge { from: start, to: end, .. } => (*start, *end) = (from, to),
+                HotRegion::Corner(_) => {
+                    let entry = section.get("range").unwrap();
+                    self.diagnostics.push(Diagnostic::warning(entry.line, entry.column, "`range` only applies to edges"));
+                }
+            }
+        }
+        if let Some(size) = check(&mut self.diagnostics, section, "size", positive) {
+            hot.size = size;
+        }
+        if let Some(dwell) = check(&mut self.diagnostics, section, "dwell", seconds) {
+            hot.dwell = dwell;
+        }
+        if let Some(cooldown) = check(&mut self.diagnostics, section, "cooldown", seconds) {
+            hot.cooldown = cooldown;
+        }
+
+        self.config.hot.push(hot);
+    }
+
+    /// `order` is what the right zone was called before there were zones
+    fn load_order(&mut self, section: &Section) {
+        if let Some(entry) = section.get("order") {
+            self.config.zones[2].modules = self.module_list(entry);
+        }
+    }
+
+    fn load_zone(&mut self, section: &Section, zone: usize) {
+        if let Some(entry) = section.get("modules") {
+            self.config.zones[zone].modules = self.module_list(entry);
+        }
+
+        let config = &mut self.config.zones[zone];
+
+        if let Some(spacing) = check(&mut self.diagnostics, section, "spacing", non_negative_float) {
+            config.spacing = spacing as f32;
+        }
+        if let Some(separator) = check(&mut self.diagnostics, section, "separator", markup_string) {
+            config.separator = separator;
+        }
+        if let Some(priority) = check(&mut self.diagnostics, section, "priority", integer) {
+            config.priority = priority;
+        }
+    }
+
+    fn module_list(&mut self, entry: &Entry) -> Vec<String> {
+        let Some(names) = verify(&mut self.diagnostics, entry, array) else { return Vec::new() };
+
+        names.iter()
+            .filter_map(|value| match value.as_str() {
+                Some(name) => {
+                    if !self.listed_at.iter().any(|(listed, _)| listed == name) {
+                        self.listed_at.push((name.to_string(), (entry.line, entry.value_column)));
+                    }
+                    Some(name.to_string())
+                }
+                None => {
+                    self.diagnostics.push(Diagnostic::error(
+                        entry.line, entry.value_column,
+                        format!("module names must be strings, found {}", value.type_name())
+                    ));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Every module named by a zone, in the order they're shown
+    fn collect_modules(&mut self) {
+        let mut modules: Vec<ModuleConfig> = Vec::new();
+
+        for name in self.config.zones.iter().flat_map(|zone| zone.modules.iter()) {
+            if !modules.iter().any(|m| &m.name == name) {
+                modules.push(ModuleConfig::new(name));
+            }
+        }
+
+        self.config.modules = modules;
+    }
+
+    fn load_modules(&mut self, document: &Document) {
+        for section in document.sections.iter() {
+            let Some(name) = section.name.strip_prefix("module.") else { continue };
+
+            if !self.config.modules.iter().any(|m| m.name == name) {
+                self.diagnostics.push(Diagnostic::warning(
+                    section.line, 1, format!("module `{name}` is not listed in any zone")
+                ));
+            }
+        }
+
+        let mut modules = std::mem::take(&mut self.config.modules);
+
+        modules.retain_mut(|module| {
+            let section = document.section(&format!("module.{}", module.name));
+            // checked once the type is known, it may come after them
+            let mut options = Vec::new();
+
+            for entry in section.iter().flat_map(|s| s.entries.iter()) {
+                match entry.key.as_str() {
+                    "type" => if let Some(kind) = verify(&mut self.diagnostics, entry, string) {
+                        module.kind = kind;
+                    }
+                    "interval" => if let Some(update) = verify(&mut self.diagnostics, entry, interval) {
+                        module.update = Some(update);
+                    }
+                    "reveal" => if let Some(duration) = verify(&mut self.diagnostics, entry, seconds) {
+                        module.reveal = Some(duration).filter(|duration| !duration.is_zero());
+                    }
+                    key if let Some((_, trigger)) = TRIGGERS.iter().find(|(name, _)| *name == key) => {
+                        if let Some(action) = verify(&mut self.diagnostics, entry, action) {
+                            module.bindings.retain(|(bound, _)| bound != trigger);
+                            module.bindings.push((*trigger, action));
+                        }
+                    }
+                    _ => options.push(entry),
+                }
+            }
+
+            for entry in options {
+                let known = OPTIONS.iter().find(|(kind, key, _)| *kind == module.kind && *key == entry.key);
+
+                match known {
+                    Some((_, _, option)) => if verify(&mut self.diagnostics, entry, |entry| module_option(entry, *option)).is_some() {
+                        module.options.push((entry.key.clone(), entry.value.clone()));
+                    }
+                    // an unknown type is reported below
+                    None if !crate::modules::KINDS.contains(&module.kind.as_str()) => {}
+                    None => self.diagnostics.push(Diagnostic::warning(
+                        entry.line, entry.column, format!("unknown key `{}` for a `{}` module", entry.key, module.kind)
+                    )),
+                }
+            }
+
+            if crate::modules::KINDS.contains(&module.kind.as_str()) {
+                return true;
+            }
+
+            let (line, column) = match section.and_then(|s| s.get("type")) {
+                Some(entry) => (entry.line, entry.value_column),
+                None => section.map_or_else(
+                    || self.listed_at.iter().find(|(listed, _)| listed == &module.name).map_or((0, 0), |(_, at)| *at),
+                    |s| (s.line, 1)
+                ),
+            };
+            self.diagnostics.push(Diagnostic::error(line, column, format!(
+                "unknown module type `{}` for module `{}`, expected one of: {}",
+                module.kind, module.name, crate::modules::KINDS.join(", ")
+            )));
+
+            false
+        });
+
+        self.config.modules = modules;
+    }
+}
+
+/// Converts `section.key` if present, otherwise reports why it was ignored
+fn check<T>(
+    diagnostics: &mut Vec<Diagnostic>,
+    section: &Section,
+    key: &str,
+    convert: impl FnOnce(&Entry) -> Result<T, String>,
+) -> Option<T> {
+    verify(diagnostics, section.get(key)?, convert)
+}
+
+fn verify<T>(
+    diagnostics: &mut Vec<Diagnostic>,
+    entry: &Entry,
+    convert: impl FnOnce(&Entry) -> Result<T, String>,
+) -> Option<T> {
+    match convert(entry) {
+        Ok(value) => Some(value),
+        Err(why) => {
+            diagnostics.push(Diagnostic::error(
+                entry.line, entry.value_column,
+                format!("`{}`: {why}, using the default", entry.key)
+            ));
+            None
+        }
+    }
+}
+
+fn mismatch(expected: &str, entry: &Entry) -> String {
+    format!("expected {expected}, found {} `{}`", entry.value.type_name(), entry.raw)
+}
+
+fn string(entry: &Entry) -> Result<String, String> {
+    entry.value.as_str()
+        .map(str::to_string)
+        .ok_or_else(|| mismatch("string", entry))
+}
+
+fn array(entry: &Entry) -> Result<Vec<Value>, String> {
+    entry.value.as_array()
+        .map(<[Value]>::to_vec)
+        .ok_or_else(|| mismatch("array", entry))
+}
+
+fn boolean(entry: &Entry) -> Result<bool, String> {
+    entry.value.as_bool().ok_or_else(|| mismatch("true or false", entry))
+}
+
+/// One of the `(name, value)` pairs in `choices`
+fn keyword<T: Copy>(entry: &Entry, choices: &[(&str, T)]) -> Result<T, String> {
+    let names: Vec<&str> = choices.iter().map(|(name, _)| *name).collect();
+
+    entry.value.as_str()
+        .and_then(|word| choices.iter().find(|(name, _)| *name == word))
+        .map(|(_, value)| *value)
+        .ok_or_else(|| mismatch(&format!("one of \"{}\"", names.join("\", \"")), entry))
+}
+
+fn edge(entry: &Entry) -> Result<Edge, String> {
+    keyword(entry, &[("top", Edge::Top), ("bottom", Edge::Bottom), ("left", Edge::Left), ("right", Edge::Right)])
+}
+
+fn layer(entry: &Entry) -> Result<Layer, String> {
+    keyword(entry, &[
+        ("background", Layer::Background), ("bottom", Layer::Bottom),
+        ("top", Layer::Top), ("overlay", Layer::Overlay),
+    ])
+}
+
+fn reveal_mode(entry: &Entry) -> Result<RevealMode, String> {
+    keyword(entry, &[("always", RevealMode::Always), ("hover", RevealMode::Hover), ("edge-drag", RevealMode::EdgeDrag)])
+}
+
+fn reveal_animation(entry: &Entry) -> Result<RevealAnimation, String> {
+    keyword(entry, &[("none", RevealAnimation::None), ("slide", RevealAnimation::Slide), ("fade", RevealAnimation::Fade)])
+}
+
+fn easing(entry: &Entry) -> Result<Easing, String> {
+    keyword(entry, &[
+        ("linear", Easing::Linear), ("ease-in", Easing::EaseIn),
+        ("ease-out", Easing::EaseOut), ("ease-in-out", Easing::EaseInOut),
+    ])
+}
+
+fn hot_region(entry: &Entry) -> Result<HotRegion, String> {
+    let edge = |edge| HotRegion::Edge { edge, from: 0.0, to: 1.0 };
+
+    keyword(entry, &[
+        ("top-left", HotRegion::Corner(Corner::TopLeft)), ("top-right", HotRegion::Corner(Corner::TopRight)),
+        ("bottom-left", HotRegion::Corner(Corner::BottomLeft)), ("bottom-right", HotRegion::Corner(Corner::BottomRight)),
+        ("top", edge(Edge::Top)), ("bottom", edge(Edge::Bottom)), ("left", edge(Edge::Left)), ("right", edge(Edge::Right)),
+    ])
+}
+
+/// `[from, to]`, fractions of a length
+fn range(entry: &Entry) -> Result<(f32, f32), String> {
+    let fraction = |value: &Value| value.as_float().filter(|f| (0.0..=1.0).contains(f));
+
+    match entry.value.as_array() {
+        Some([from, to]) => match (fraction(from), fraction(to)) {
+            (Some(from), Some(to)) if from < to => Ok((from as f32, to as f32)),
+            _ => Err(format!("expected [from, to] between 0 and 1 with from < to, found `{}`", entry.raw)),
+        },
+        _ => Err(mismatch("[from, to]", entry)),
+    }
+}
+
+/// A shell command, or one of the bar's own actions written `@name`
+fn action(entry: &Entry) -> Result<Action, String> {
+    let text = string(entry)?;
+
+    match text.strip_prefix('@') {
+        Some("toggle-pin") => Ok(Action::TogglePin),
+        Some("reload") => Ok(Action::Reload),
+        Some("refresh") => Ok(Action::Refresh),
+        Some(name) => Err(format!("unknown action `@{name}`, expected one of: @toggle-pin, @reload, @refresh")),
+        None if text.trim().is_empty() => Err("empty command".into()),
+        None => Ok(Action::Command(text)),
+    }
+}
+
+/// `margin = 4` for every side, or `[top, right, bottom, left]`
+fn margins(entry: &Entry) -> Result<Margins, String> {
+    let expected = || mismatch("integer or [top, right, bottom, left]", entry);
+    let pixels = |value: &Value| value.as_int().and_then(|i| i32::try_from(i).ok());
+
+    if let Some(all) = pixels(&entry.value) {
+        return Ok(Margins { top: all, right: all, bottom: all, left: all });
+    }
+
+    match entry.value.as_array().ok_or_else(expected)? {
+        [top, right, bottom, left] => Ok(Margins {
+            top: pixels(top).ok_or_else(expected)?,
+            right: pixels(right).ok_or_else(expected)?,
+            bottom: pixels(bottom).ok_or_else(expected)?,
+            left: pixels(left).ok_or_else(expected)?,
+        }),
+        _ => Err(expected()),
+    }
+}
+
+/// A string with `<span>` markup, kept as it is once it's known to parse
+fn markup_string(entry: &Entry) -> Result<String, String> {
+    let text = string(entry)?;
+    markup::parse(&text)?;
+    Ok(text)
+}
+
+fn strings(entry: &Entry) -> Result<Vec<String>, String> {
+    array(entry)?.iter()
+        .map(|value| value.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("expected strings, found {}", value.type_name())))
+        .collect()
+}
+
+fn positive(entry: &Entry) -> Result<u32, String> {
+    entry.value.as_int()
+        .and_then(|i| u32::try_from(i).ok())
+        .filter(|i| *i > 0)
+        .ok_or_else(|| mismatch("positive integer", entry))
+}
+
+fn non_negative_float(entry: &Entry) -> Result<f64, String> {
+    entry.value.as_float()
+        .filter(|f| *f >= 0.0)
+        .ok_or_else(|| mismatch("number that isn't negative", entry))
+}
+
+fn seconds(entry: &Entry) -> Result<Duration, String> {
+    non_negative_float(entry)
+        .map(Duration::from_secs_f64)
+        .map_err(|_| mismatch("number of seconds", entry))
+}
+
+fn integer(entry: &Entry) -> Result<i64, String> {
+    entry.value.as_int().ok_or_else(|| mismatch("integer", entry))
+}
+
+fn positive_float(entry: &Entry) -> Result<f64, String> {
+    entry.value.as_float()
+        .filter(|f| *f > 0.0)
+        .ok_or_else(|| mismatch("positive number", entry))
+}
+
+/// Seconds between refreshes, or `"event"` for modules that are only refreshed on demand
+fn interval(entry: &Entry) -> Result<Update, String> {
+    if entry.value.as_str() == Some("event") {
+        return Ok(Update::EventDriven);
+    }
+
+    positive_float(entry)
+        .map(|seconds| Update::Every(Duration::from_secs_f64(seconds)))
+        .map_err(|_| mismatch("number of seconds or \"event\"", entry))
+}
+
+/// `"JetBrains Mono 11, Noto Sans Symbols"`, the size after the first family is in points
+fn font_list(entry: &Entry) -> Result<(Vec<String>, Option<f32>), String> {
+    let list = string(entry)?;
+    let mut size = None;
+    let mut families = Vec::new();
+
+    for (index, item) in list.split(',').map(str::trim).enumerate() {
+        // only the first font takes a size, fallbacks like `Noto Sans Symbols 2` end in digits
+        let mut family = item;
+
+        if index == 0 && let Some((name, points)) = item.rsplit_once(' ')
+            && let Ok(points) = points.parse::<f32>()
+            && points > 0.0
+        {
+            family = name.trim();
+            size = Some(points * 96.0 / 72.0);
+        }
+
+        if family.is_empty() {
+            return Err(format!("empty font name in `{list}`"));
+        }
+
+        families.push(family.to_string());
+    }
+
+    Ok((families, size))
+}
+
+/// Bare `background=000000` parses as a number, so colors go by the source text
+/// A module's own option, only checked, the module reads it itself
+fn module_option(entry: &Entry, option: OptionType) -> Result<(), String> {
+    match option {
+        OptionType::Number { min, max } => entry.value.as_float()
+            .filter(|f| (min..=max).contains(f))
+            .map(drop)
+            .ok_or_else(|| mismatch(&format!("number from {min} to {max}"), entry)),
+        OptionType::Integer { min, max } => entry.value.as_int()
+            .filter(|i| (min..=max).contains(i))
+            .map(drop)
+            .ok_or_else(|| mismatch(&format!("whole number from {min} to {max}"), entry)),
+        OptionType::Color => Color::parse(&string(entry)?).map(drop),
+        OptionType::Format => crate::modules::check_format(&string(entry)?),
+    }
+}
+
+fn color(entry: &Entry) -> Result<Color, String> {
+    Color::parse(entry.value.as_str().unwrap_or(&entry.raw))
 }
 
 impl Default for ConfigState {
     fn default() -> Self {
         Self {
-            bar_color: Color { r: 0, g: 0, b: 0 },
-            text_color: Color { r: 255, g: 255, b: 255 },
+            bar: BarConfig {
+                width: None,
+                height: 24,
+                outputs: Vec::new(),
+                edge: Edge::Top,
+                layer: Layer::Top,
+                margins: Margins::default(),
+                exclusive: false,
+                rotate_text: false,
+                background: Color::rgb(0, 0, 0),
+                foreground: Color::rgb(255, 255, 255),
+            },
+            font: FontConfig {
+                families: vec!["C059".into(), "sans-serif".into()],
+                size: 20.0,
+                gamma_correct: false,
+            },
+            reveal: RevealConfig {
+                mode: RevealMode::EdgeDrag,
+                delay: Duration::from_millis(300),
+                hide_delay: Duration::ZERO,
+                min_visible: Duration::ZERO,
+                animation: RevealAnimation::None,
+                duration: Duration::from_millis(200),
+                easing: Easing::EaseOut,
+            },
+            hot: vec![HotConfig::new(
+                "overview",
+                HotRegion::Corner(Corner::BottomLeft),
+                Action::Command("niri msg action open-overview".into()),
+            )],
+            zones: [
+                ZoneConfig::new(1),
+                ZoneConfig::new(0),
+                ZoneConfig { modules: vec!["audio".into(), "clock".into()], ..ZoneConfig::new(2) },
+            ],
+            modules: vec![
+                ModuleConfig::new("audio"),
+                ModuleConfig::new("clock"),
+            ],
         }
     }
 }
+
+#[test]
+fn legacy_config() {
+    let (config, diagnostics) = ConfigState::from_source("background=102030\nforeground=ffffff\n");
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.bar.background, Color::rgb(0x10, 0x20, 0x30));
+    assert_eq!(config.bar.foreground, Color::rgb(0xff, 0xff, 0xff));
+    assert_eq!(config.modules, ConfigState::default().modules);
+}
+
+#[test]
+fn module_tables() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[modules]\n",
+        "order = [\"utc\", \"clock\"]\n",
+        "[bar]\n",
+        "outputs = [\"DP-1\", \"HDMI-A-1\"]\n",
+        "[module.utc]\n",
+        "type = \"clock\"\n",
+        "utc_offset = 0\n",
+        "interval = 0.5\n",
+        "[module.clock]\n",
+        "interval = \"event\"\n",
+        "reveal = 2\n",
+        "on_click = \"@refresh\"\n",
+        "on_scroll_up = \"brightnessctl set +5%\"\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.bar.outputs, vec!["DP-1", "HDMI-A-1"]);
+    assert_eq!(config.modules.len(), 2);
+    assert_eq!(config.modules[0].kind, "clock");
+    assert_eq!(config.modules[0].get_float("utc_offset"), Some(0.0));
+    assert_eq!(config.modules[0].update, Some(Update::Every(Duration::from_millis(500))));
+    assert_eq!(config.modules[1].kind, "clock");
+    assert_eq!(config.modules[1].update, Some(Update::EventDriven));
+    assert_eq!(config.modules[1].reveal, Some(Duration::from_secs(2)));
+    assert_eq!(config.modules[1].bindings, vec![
+        (Trigger::LeftClick, Action::Refresh),
+        (Trigger::ScrollUp, Action::Command("brightnessctl set +5%".into())),
+    ]);
+
+    // a bad option is left out and the module falls back to its default
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[module.clock]\n",
+        "format = \"%H:%Q\"\n",
+        "seconds_alpha = 1\n",
+        "utc_offset = 30\n",
+        "colour = \"red\"\n",
+        "[module.audio]\n",
+        "muted_color = \"red\\\" bg=\\\"blue\"\n",
+        "step = -5\n",
+        "max_volume = 500\n",
+    ));
+    assert_eq!(diagnostics.len(), 6, "{diagnostics:?}");
+    assert_eq!(diagnostics.iter().filter(|d| d.message.starts_with("unknown key")).count(), 1);
+    assert_eq!(config.modules[1].options, vec![("seconds_alpha".to_string(), Value::Integer(1))]);
+    assert!(config.modules[0].options.is_empty());
+
+    // scroll steps are whole percents
+    let (config, diagnostics) = ConfigState::from_source("[module.audio]\nstep = 0.5\nmax_volume = 150\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(config.modules[0].get_int("step"), None);
+    assert_eq!(config.modules[0].get_int("max_volume"), Some(150));
+}
+
+#[test]
+fn zones() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[zone.left]\n",
+        "modules = [\"audio\", \"utc\"]\n",
+        "separator = \"|\"\n",
+        "[zone.center]\n",
+        "modules = [\"clock\"]\n",
+        "priority = 5\n",
+        "[zone.right]\n",
+        "modules = []\n",
+        "[module.utc]\n",
+        "type = \"clock\"\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.zones[0].modules, vec!["audio", "utc"]);
+    assert_eq!(config.zones[0].separator, "|");
+    assert_eq!(config.zones[1].priority, 5);
+    assert!(config.zones[2].modules.is_empty());
+
+    let names: Vec<&str> = config.modules.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["audio", "utc", "clock"]);
+}
+
+#[test]
+fn font_families() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[font]\n",
+        "family = \"JetBrains Mono 12, Noto Sans Symbols 2, Noto Color Emoji\"\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.font.families, vec!["JetBrains Mono", "Noto Sans Symbols 2", "Noto Color Emoji"]);
+    assert_eq!(config.font.size, 16.0);
+}
+
+#[test]
+fn bar_placement() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[bar]\n",
+        "edge = \"bottom\"\n",
+        "layer = \"overlay\"\n",
+        "margin = [0, 8, 4, 8]\n",
+        "exclusive = true\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.bar.edge, Edge::Bottom);
+    assert_eq!(config.bar.layer, Layer::Overlay);
+    assert_eq!(config.bar.margins, Margins { top: 0, right: 8, bottom: 4, left: 8 });
+    assert!(config.bar.exclusive);
+
+    let (config, diagnostics) = ConfigState::from_source("[bar]\nedge = \"middle\"\nmargin = 6\n");
+    assert_eq!(config.bar.edge, Edge::Top);
+    assert_eq!(config.bar.margins.left, 6);
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+fn reveal() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[reveal]\n",
+        "mode = \"hover\"\n",
+        "delay = 0.5\n",
+        "hide_delay = 1\n",
+        "animation = \"fade\"\n",
+        "duration = 0.1\n",
+        "easing = \"linear\"\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.reveal, RevealConfig {
+        mode: RevealMode::Hover,
+        delay: Duration::from_millis(500),
+        hide_delay: Duration::from_secs(1),
+        min_visible: Duration::ZERO,
+        animation: RevealAnimation::Fade,
+        duration: Duration::from_millis(100),
+        easing: Easing::Linear,
+    });
+
+    let (config, diagnostics) = ConfigState::from_source("[reveal]\nmode = \"peek\"\ndelay = -1\n");
+    assert_eq!(config.reveal, ConfigState::default().reveal);
+    assert_eq!(diagnostics.len(), 2);
+}
+
+#[test]
+fn hot_regions() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "[hot.pin]\n",
+        "region = \"right\"\n",
+        "range = [0.25, 0.75]\n",
+        "size = 2\n",
+        "dwell = 0.5\n",
+        "cooldown = 0\n",
+        "action = \"@toggle-pin\"\n",
+        "[hot.menu]\n",
+        "region = \"top-left\"\n",
+        "action = \"fuzzel\"\n",
+    ));
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(config.hot, vec![
+        HotConfig {
+            name: "pin".into(),
+            region: HotRegion::Edge { edge: Edge::Right, from: 0.25, to: 0.75 },
+            size: 2,
+            dwell: Duration::from_millis(500),
+            cooldown: Duration::ZERO,
+            action: Action::TogglePin,
+        },
+        HotConfig::new("menu", HotRegion::Corner(Corner::TopLeft), Action::Command("fuzzel".into())),
+    ]);
+
+    let (config, diagnostics) = ConfigState::from_source("[input]\noverview_command = \"\"\n");
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert!(config.hot.is_empty());
+
+    let (config, diagnostics) = ConfigState::from_source("[hot.x]\naction = \"@launch\"\n");
+    assert!(config.hot.is_empty());
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+fn bad_values_fall_back_per_key() {
+    let (config, diagnostics) = ConfigState::from_source(concat!(
+        "background=#ffff0\n",
+        "foreground=#ä0000\n",
+        "[bar]\n",
+        "height = 30\n",
+        "width = wide\n",
+        "[modules]\n",
+        "order = [\"clock\", \"weather\"]\n",
+    ));
+
+    let defaults = ConfigState::default();
+    assert_eq!(config.bar.background, defaults.bar.background);
+    assert_eq!(config.bar.foreground, defaults.bar.foreground);
+    assert_eq!(config.bar.width, defaults.bar.width);
+    assert_eq!(config.bar.height, 30);
+    assert_eq!(config.modules, vec![ModuleConfig::new("clock")]);
+
+    let positions: Vec<_> = diagnostics.iter().map(|d| (d.line, d.column)).collect();
+    assert_eq!(positions, vec![(1, 12), (2, 12), (5, 9), (7, 9)]);
+}
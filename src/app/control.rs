@@ -0,0 +1,262 @@
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic,
+    Interest, LoopHandle, Mode, PostAction,
+};
+
+use super::BarWindow;
+use super::config::{Action, RevealMode};
+use super::visibility::Visibility;
+
+/// Longest line a client can send, anything longer closes the connection
+const MAX_LINE: usize = 4096;
+/// How long the bar waits on a client that doesn't take its answer, the event loop is stuck meanwhile
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A line sent to the control socket
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    /// Shows every bar, for a while or until `hide`
+    Show(Option<Duration>),
+    Hide,
+    TogglePin,
+    Reload,
+    /// Refreshes the module with this name
+    Refresh(String),
+    State,
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words[..] {
+            ["show"] => Ok(Request::Show(None)),
+            ["show", seconds] => seconds.parse::<f64>().ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .map(|duration| Request::Show(Some(duration)))
+                .ok_or_else(|| format!("`{seconds}` isn't a number of seconds")),
+            ["hide"] => Ok(Request::Hide),
+            ["toggle-pin"] => Ok(Request::TogglePin),
+            ["reload"] => Ok(Request::Reload),
+            ["module", name, "refresh"] => Ok(Request::Refresh(name.to_string())),
+            ["get", "state"] => Ok(Request::State),
+            [] => Err("empty command".into()),
+            _ => Err(format!("unknown command `{}`", line.trim())),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/svbar-<wayland display>.sock`, so every session gets its own.
+/// None without `XDG_RUNTIME_DIR`, anywhere else other users could get at the socket.
+pub fn socket_path() -> Option<PathBuf> {
+    let directory = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty())?);
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".into());
+
+    Some(directory.join(format!("svbar-{}.sock", display.replace('/', "_"))))
+}
+
+/// Listens on the control socket, returns its path to remove on exit.
+/// Takes one command per line and answers each with a line: `ok`, `error: <why>`, or JSON for `get state`.
+///
+/// `show [seconds]`, `hide`, `toggle-pin`, `reload`, `module <name> refresh`, `get state`
+pub fn serve_control(handle: &LoopHandle<'static, BarWindow>) -> Option<PathBuf> {
+    let Some(path) = socket_path() else {
+        eprintln!("XDG_RUNTIME_DIR isn't set, not listening for control commands");
+        return None;
+    };
+
+    // a socket nobody answers on is left over from a bar that crashed
+    if UnixStream::connect(&path).is_ok() {
+        eprintln!("Not listening on {}, another svbar already is", path.display());
+        return None;
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .inspect_err(|why| eprintln!("Failed to listen on {}: {why}", path.display()))
+        .ok()?;
+
+    handle
+        .insert_source(Generic::new(listener, Interest::READ, Mode::Level), |_, listener, window| {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => serve(&window.state.loop_handle, stream),
+                    Err(why) if why.kind() == ErrorKind::WouldBlock => break,
+                    Err(why) => {
+                        eprintln!("Failed to accept a control connection: {why}");
+                        break;
+                    }
+                }
+            }
+            Ok(PostAction::Continue)
+        })
+        .inspect_err(|why| eprintln!("Failed to insert the control socket source: {why}"))
+        .ok()?;
+
+    Some(path)
+}
+
+/// Answers the lines coming in on `stream` until the client hangs up
+fn serve(handle: &LoopHandle<'static, BarWindow>, stream: UnixStream) {
+    // blocking, so answers go out whole, it's only read from once there's something to read
+    if let Err(why) = stream.set_nonblocking(false).and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT))) {
+        eprintln!("Failed to set up a control connection: {why}");
+        return;
+    }
+
+    let mut pending = Vec::new();
+    let source = Generic::new(stream, Interest::READ, Mode::Level);
+
+    let inserted = handle.insert_source(source, move |_, stream, window| {
+        let mut stream: &UnixStream = stream;
+        let mut buffer = [0; 1024];
+
+        let hung_up = match stream.read(&mut buffer) {
+            Ok(0) => true,
+            Ok(read) => {
+                pending.extend_from_slice(&buffer[..read]);
+                false
+            }
+            Err(why) if why.kind() == ErrorKind::WouldBlock => return Ok(PostAction::Continue),
+            Err(why) => {
+                eprintln!("Failed to read from a control connection: {why}");
+                return Ok(PostAction::Remove);
+            }
+        };
+
+        // the last line doesn't need a newline
+        let mut lines: Vec<Vec<u8>> = Vec::new();
+        while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+            lines.push(pending.drain(..=end).collect());
+        }
+        if hung_up && !pending.is_empty() {
+            lines.push(std::mem::take(&mut pending));
+        }
+
+        for line in lines {
+            let reply = window.control(String::from_utf8_lossy(&line).trim());
+
+            match stream.write_all(format!("{reply}\n").as_bytes()) {
+                Ok(()) => {}
+                // the client didn't wait for the answer
+                Err(why) if why.kind() == ErrorKind::BrokenPipe => return Ok(PostAction::Remove),
+                Err(why) => {
+                    eprintln!("Failed to answer a control connection: {why}");
+                    return Ok(PostAction::Remove);
+                }
+            }
+        }
+
+        if hung_up || pending.len() > MAX_LINE {
+            return Ok(PostAction::Remove);
+        }
+        Ok(PostAction::Continue)
+    });
+
+    if let Err(why) = inserted {
+        eprintln!("Failed to insert a control connection: {why}");
+    }
+}
+
+impl BarWindow {
+    /// Runs a line sent to the control socket, returns the answer
+    pub fn control(&mut self, line: &str) -> String {
+        let request = match Request::parse(line) {
+            Ok(request) => request,
+            Err(why) => return format!("error: {why}"),
+        };
+        let now = Instant::now();
+
+        match request {
+            Request::Show(duration) => for index in 0..self.bars.len() {
+                let reveal = &mut self.bars[index].reveal;
+                match duration {
+                    Some(duration) => reveal.show_for(&self.config.reveal, duration, now),
+                    None => reveal.pin(&self.config.reveal, now),
+                };
+                self.reveal_changed(index);
+            }
+            Request::Hide => for index in 0..self.bars.len() {
+                if self.bars[index].reveal.hide(&self.config.reveal, now) {
+                    self.reveal_changed(index);
+                }
+            }
+            Request::TogglePin => for index in 0..self.bars.len() {
+                self.run_action(index, None, Action::TogglePin);
+            }
+            Request::Reload => self.reload_config(),
+            Request::Refresh(name) => {
+                let Some(id) = self.state.modules.iter().find(|slot| slot.config.name == name).map(|slot| slot.id) else {
+                    return format!("error: no module `{name}`");
+                };
+                self.refresh_module(id);
+            }
+            Request::State => return self.state_json(),
+        }
+
+        "ok".into()
+    }
+
+    /// `{"bars":[{"output":"DP-1","visibility":"revealed","exclusive":false}],"modules":[{"name":"clock","output":"…"}]}`
+    fn state_json(&self) -> String {
+        let bars: Vec<String> = self.bars.iter()
+            .map(|bar| {
+                let output = self.wayland.output_state.info(&bar.output).and_then(|info| info.name).unwrap_or_default();
+                let exclusive = self.config.bar.exclusive;
+                // what's on screen, an exclusive bar is drawn in full whatever its reveal state
+                let visibility = match bar.reveal.visibility {
+                    _ if exclusive || self.config.reveal.mode == RevealMode::Always => "revealed",
+                    Visibility::Hidden => "hidden",
+                    Visibility::Peeking { .. } => "peeking",
+                    Visibility::Revealed => "revealed",
+                    Visibility::Pinned => "pinned",
+                };
+                format!("{{\"output\":{},\"visibility\":\"{visibility}\",\"exclusive\":{exclusive}}}", json_string(&output))
+            })
+            .collect();
+
+        let modules: Vec<String> = self.state.modules.iter()
+            .map(|slot| format!("{{\"name\":{},\"output\":{}}}", json_string(&slot.config.name), json_string(&slot.output)))
+            .collect();
+
+        format!("{{\"bars\":[{}],\"modules\":[{}]}}", bars.join(","), modules.join(","))
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+#[test]
+fn requests_are_parsed() {
+    assert_eq!(Request::parse("show"), Ok(Request::Show(None)));
+    assert_eq!(Request::parse(" show 1.5 "), Ok(Request::Show(Some(Duration::from_millis(1500)))));
+    assert_eq!(Request::parse("module clock refresh"), Ok(Request::Refresh("clock".into())));
+    assert_eq!(Request::parse("get state"), Ok(Request::State));
+
+    assert!(Request::parse("show -1").is_err());
+    assert!(Request::parse("").is_err());
+    assert!(Request::parse("module clock").is_err());
+
+    assert_eq!(json_string("a \"b\"\n\u{1}"), "\"a \\\"b\\\"\\n\\u0001\"");
+}
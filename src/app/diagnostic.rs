@@ -0,0 +1,97 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, line, column, message: message.into() }
+    }
+
+    pub fn warning(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, line, column, message: message.into() }
+    }
+}
+
+/// Everything that went wrong while loading one file, printable as a rustc-like report
+#[derive(Debug, Default)]
+pub struct Report {
+    pub path: String,
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut diagnostics: Vec<&Diagnostic> = self.diagnostics.iter().collect();
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+
+        for d in diagnostics {
+            let label = match d.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+
+            writeln!(f, "{label}: {}", d.message)?;
+
+            // line 0 is used for problems that aren't tied to a line, like an unreadable file
+            if d.line == 0 {
+                writeln!(f, " --> {}", self.path)?;
+                continue;
+            }
+
+            writeln!(f, " --> {}:{}:{}", self.path, d.line, d.column)?;
+
+            if let Some(text) = self.source.lines().nth(d.line - 1) {
+                let number = d.line.to_string();
+                let pad = " ".repeat(number.len());
+                let caret = " ".repeat(d.column.saturating_sub(1));
+
+                writeln!(f, "{pad} |")?;
+                writeln!(f, "{number} | {text}")?;
+                writeln!(f, "{pad} | {caret}^")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn report_points_at_column() {
+    let report = Report {
+        path: "config".into(),
+        source: "[bar]\nheight = tall\n".into(),
+        diagnostics: vec![Diagnostic::error(2, 10, "expected integer")],
+    };
+
+    assert!(report.has_errors());
+    assert_eq!(report.to_string(), concat!(
+        "error: expected integer\n",
+        " --> config:2:10\n",
+        "  |\n",
+        "2 | height = tall\n",
+        "  |          ^\n",
+    ));
+}
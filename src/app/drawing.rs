@@ -2,41 +2,58 @@ use super::BarWindow;
 
 use smithay_client_toolkit::{
     shell::WaylandSurface,
-    shm::{slot::{Buffer, SlotPool}, Shm}
+    shm::{slot::SlotPool, Shm}
 };
 use wayland_client::{QueueHandle, globals::GlobalList, protocol::wl_shm};
-use ab_glyph::{Font, FontRef, ScaleFont, point};
 
+use std::time::Instant;
+
+use super::animation::Appearance;
+use super::render::{Canvas, Fonts, Frame, render};
+use super::scale::SCALE_DENOMINATOR;
 
-pub struct GraphicsState {
-    pub width: u32,
-    pub height: u32,
 
+/// Shared by all bars
+pub struct GraphicsState {
     pub shm: Shm,
+    /// Grows as the bars need bigger buffers
     pub pool: SlotPool,
-    pub buffer: Option<Buffer>,
+    pub fonts: Fonts,
 }
 
 impl GraphicsState {
-    pub fn new(width: u32, height: u32, globals: &GlobalList, qh: &QueueHandle<BarWindow>) -> Self {
+    pub fn new(globals: &GlobalList, qh: &QueueHandle<BarWindow>) -> Self {
         let shm = Shm::bind(globals, qh).expect("wl_shm not available");
-        let pool = SlotPool::new((width * height * 4) as usize, &shm).expect("Failed to create pool");
+        let pool = SlotPool::new(1920 * 24 * 4, &shm).expect("Failed to create pool");
 
-        Self {
-            width, height,
-            shm, pool,
-            buffer: None
-        }
+        Self { shm, pool, fonts: Fonts::default() }
     }
 }
 
 impl BarWindow {
-    pub (super) fn draw(&mut self, qh: &QueueHandle<Self>) {
-        let width  = self.graphics.width;
-        let height = self.graphics.height;
-        let stride = self.graphics.width as i32 * 4;
+    /// Redraws every bar
+    pub fn request_redraw(&mut self) {
+        (0..self.bars.len()).for_each(|index| self.request_bar_redraw(index));
+    }
+
+    /// Draws right away, or once the compositor is done with the last frame
+    pub fn request_bar_redraw(&mut self, index: usize) {
+        let bar = &mut self.bars[index];
+        bar.dirty = true;
+
+        if !bar.frame_pending && !bar.first_configure {
+            let qh = self.wayland.qh.clone();
+            self.draw(index, &qh);
+        }
+    }
 
-        let buffer = self.graphics.buffer.get_or_insert_with(|| {
+    pub (super) fn draw(&mut self, index: usize, qh: &QueueHandle<Self>) {
+        let bar = &mut self.bars[index];
+        let (width, height) = bar.physical_size();
+        let stride = width as i32 * 4;
+        let scale = bar.scale_factor();
+
+        let buffer = bar.buffer.get_or_insert_with(|| {
             self.graphics.pool
                 .create_buffer(width as i32, height as i32, stride, wl_shm::Format::Argb8888)
                 .expect("create buffer")
@@ -64,84 +81,102 @@ impl BarWindow {
         };
 
         {
-            canvas.chunks_exact_mut(4).enumerate().for_each(|(index, chunk)| {
-                let color: i32 = if index % (height + 1) as usize <= 30 &&
-                    index % width as usize <= self.state.bar_width as usize
-                {
-                    let hex = self.config.bar_color.as_hex();
-                    let alpha = 0xFF << 24;
-
-                    alpha + hex
-                }
-                else {
-                    0x00 << 24
-                };
-
-                let array: &mut [u8; 4] = chunk.try_into().unwrap();
-                *array = color.to_le_bytes();
-            });
-
-            let font_data = std::fs::read(
-                "/usr/share/fonts/urw-fonts/C059-Roman.otf"
-            ).unwrap();
-            let font = FontRef::try_from_slice(&font_data).unwrap();
-
-            let text = self.state.get_modules_display();
-            let text_width: f32 = text
-                .chars()
-                .map(|c| font.as_scaled(20.0).h_advance(font.glyph_id(c)))
-                .sum();
-
-            let mut pen_x = self.state.bar_width as f32 - text_width - 8.0;
-
-            for ch in text.chars() {
-                let glyph = font
-                    .glyph_id(ch)
-                    .with_scale_and_position(20.0, point(pen_x, 18.0));
-
-                if let Some(glyph) = font.outline_glyph(glyph) {
-                    let bb = glyph.px_bounds();
-
-                    glyph.draw(|gx, gy, v| {
-                        let x = bb.min.x + gx as f32;
-                        let y = (bb.min.y + gy as f32).floor();
-
-                        if x < 0.0 || y < 0.0 {
-                            return;
-                        }
-
-                        let x = x as u32;
-                        let y = y as u32;
-
-                        if x >= width || y >= height {
-                            return;
-                        }
-
-                        let idx = ((y * width + x) * 4) as usize;
-
-                        let (r,g,b) = {
-                            let color = &self.config.text_color;
-                            (
-                                color.r as f32,
-                                color.g as f32,
-                                color.b as f32,
-                            )
-                        };
-
-                        canvas[idx + 0] = (b * v) as u8; // B
-                        canvas[idx + 1] = (g * v) as u8; // G
-                        canvas[idx + 2] = (r * v) as u8; // R
-                        canvas[idx + 3] = 0xff;          // A
-                    });
+            let vertical = self.config.bar.edge.is_vertical();
+            let length = if vertical { height } else { width };
+
+            // an exclusive bar has its space reserved anyway, so it's never hidden
+            let appearance = match self.config.bar.exclusive {
+                true => Appearance { length: length as f32 / scale, opacity: 1.0 },
+                false => {
+                    let shown = bar.reveal.shown(self.config.reveal.mode, length as f32 / scale);
+                    bar.animation.update(&self.config.reveal, bar.reveal.visibility, shown, Instant::now())
                 }
+            };
+
+            let frame = Frame {
+                config: &self.config,
+                modules: self.state.modules.iter()
+                    .map(|slot| (slot.config.name.as_str(), slot.spans.as_slice()))
+                    .collect(),
+                scale,
+                revealed: (appearance.length * scale) as usize,
+                opacity: appearance.opacity,
+            };
+
+            bar.hitboxes = render(&mut Canvas { pixels: canvas, width, height }, &mut self.graphics.fonts, &frame);
+        }
 
-                pen_x += font.as_scaled(20.0).h_advance(font.glyph_id(ch));
+        // an animation is drawn frame by frame until it's done, then drawing stops until something changes
+        let animating = bar.animation.is_running();
+        bar.dirty = animating;
+
+        let damage = damage_region(&bar.previous, canvas, width);
+        if damage.is_none() && !animating {
+            return;
+        }
+
+        let surface = bar.surface.wl_surface();
+
+        if let Some((x, y, w, h)) = damage {
+            bar.previous.clear();
+            bar.previous.extend_from_slice(canvas);
+
+            match &bar.fractional {
+                Some(fractional) => fractional.viewport.set_destination(bar.width as i32, bar.height as i32),
+                None => surface.set_buffer_scale((bar.scale / SCALE_DENOMINATOR) as i32),
             }
+
+            surface.damage_buffer(x, y, w, h);
+            buffer.attach_to(surface).expect("buffer attach");
         }
 
-        self.wayland.surface.wl_surface().damage_buffer(0, 0, width as i32, height as i32);
-        self.wayland.surface.wl_surface().frame(qh, self.wayland.surface.wl_surface().clone());
-        buffer.attach_to(self.wayland.surface.wl_surface()).expect("buffer attach");
-        self.wayland.surface.commit();
+        surface.frame(qh, surface.clone());
+        bar.surface.commit();
+
+        bar.frame_pending = true;
+    }
+}
+
+/// Bounding box (x, y, width, height) of the pixels that differ between two frames,
+/// everything if the size changed
+fn damage_region(previous: &[u8], current: &[u8], width: u32) -> Option<(i32, i32, i32, i32)> {
+    let stride = width as usize * 4;
+    let height = current.len() / stride;
+
+    if previous.len() != current.len() {
+        return Some((0, 0, width as i32, height as i32));
+    }
+
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    for (y, (old, new)) in previous.chunks_exact(stride).zip(current.chunks_exact(stride)).enumerate() {
+        if old == new {
+            continue;
+        }
+
+        let first = old.chunks_exact(4).zip(new.chunks_exact(4)).position(|(a, b)| a != b).unwrap();
+        let last = old.chunks_exact(4).zip(new.chunks_exact(4)).rposition(|(a, b)| a != b).unwrap();
+
+        bounds = Some(match bounds {
+            None => (first, y, last, y),
+            Some((x0, y0, x1, _)) => (x0.min(first), y0, x1.max(last), y),
+        });
     }
+
+    bounds.map(|(x0, y0, x1, y1)| (x0 as i32, y0 as i32, (x1 - x0 + 1) as i32, (y1 - y0 + 1) as i32))
+}
+
+#[test]
+fn damage_covers_changed_pixels() {
+    let width = 4;
+    let previous = vec![0u8; 4 * 4 * 3];
+
+    assert_eq!(damage_region(&previous, &previous, width), None);
+    assert_eq!(damage_region(&[], &previous, width), Some((0, 0, 4, 3)));
+
+    let mut current = previous.clone();
+    current[(4 + 1) * 4] = 0xff;
+    current[(2 * 4 + 2) * 4 + 3] = 0xff;
+
+    assert_eq!(damage_region(&previous, &current, width), Some((1, 1, 2, 2)));
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use ab_glyph::{Font, FontVec, GlyphId, ScaleFont, point};
+
+use super::font_lookup::FontLookup;
+
+/// Glyphs are rasterized at this many horizontal offsets per pixel
+const SUBPIXEL_STEPS: f32 = 4.0;
+/// Dropping the cache once it grows this big keeps a long running bar from hoarding glyphs
+const CACHE_LIMIT: usize = 1024;
+
+#[derive(Debug)]
+pub enum FontError {
+    NotFound { family: String },
+    Read { path: String, why: std::io::Error },
+    Parse { path: String },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::NotFound { family } => write!(f, "no installed font matches `{family}`"),
+            FontError::Read { path, why } => write!(f, "failed to read font `{path}`: {why}"),
+            FontError::Parse { path } => write!(f, "`{path}` is not a font file svbar can read"),
+        }
+    }
+}
+
+/// Coverage of a rasterized glyph, `left`/`top` are relative to the pen position on the baseline
+pub struct RasterGlyph {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub coverage: Vec<u8>,
+}
+
+/// A glyph of one of the fonts in the fallback chain
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Glyph {
+    face: usize,
+    id: GlyphId,
+}
+
+#[derive(Hash, PartialEq, Eq)]
+struct GlyphKey {
+    glyph: Glyph,
+    size: u32,
+    subpixel: u8,
+}
+
+pub struct FontState {
+    /// The configured chain, to tell whether a new config needs a reload
+    pub families: Vec<String>,
+    /// Fonts of the chain that couldn't be loaded, the rest is used without them
+    pub missing: Vec<FontError>,
+
+    faces: Vec<FontVec>,
+    glyphs: HashMap<GlyphKey, Option<RasterGlyph>>,
+}
+
+impl FontState {
+    /// Loads every font of the chain that can be found.
+    /// Fails only if none of them can, with the error of the first one.
+    pub fn load(families: &[String]) -> Result<Self, FontError> {
+        let mut lookup = FontLookup::default();
+        let mut faces = Vec::new();
+        let mut missing = Vec::new();
+
+        for family in families {
+            let face = lookup.find(family)
+                .ok_or_else(|| FontError::NotFound { family: family.clone() })
+                .and_then(|path| load_face(&path.to_string_lossy()));
+
+            match face {
+                Ok(face) => faces.push(face),
+                Err(why) => missing.push(why),
+            }
+        }
+
+        if faces.is_empty() {
+            return Err(missing.into_iter().next().unwrap_or(FontError::NotFound { family: String::new() }));
+        }
+
+        Ok(Self { families: families.to_vec(), missing, faces, glyphs: HashMap::new() })
+    }
+
+    /// First font of the chain that has `c`, or the primary font's `.notdef`
+    pub fn glyph(&self, c: char) -> Glyph {
+        self.faces.iter()
+            .enumerate()
+            .map(|(face, font)| Glyph { face, id: font.glyph_id(c) })
+            .find(|glyph| glyph.id.0 != 0)
+            .unwrap_or(Glyph { face: 0, id: GlyphId(0) })
+    }
+
+    pub fn advance(&self, glyph: Glyph, size: f32) -> f32 {
+        self.faces[glyph.face].as_scaled(size).h_advance(glyph.id)
+    }
+
+    /// Baseline that vertically centers the primary font in `height` pixels
+    pub fn baseline(&self, size: f32, height: u32) -> i32 {
+        let scaled = self.faces[0].as_scaled(size);
+        ((height as f32 + scaled.ascent() + scaled.descent()) / 2.0).round() as i32
+    }
+
+    /// Distance between the baselines of two lines of the primary font
+    pub fn line_height(&self, size: f32) -> f32 {
+        let scaled = self.faces[0].as_scaled(size);
+        scaled.ascent() - scaled.descent() + scaled.line_gap()
+    }
+
+    /// Returns the glyph for a pen at `pen_x` together with the whole pixel it's drawn from
+    pub fn rasterize(&mut self, glyph: Glyph, size: f32, pen_x: f32) -> (i32, Option<&RasterGlyph>) {
+        let origin = pen_x.floor();
+        let subpixel = ((pen_x - origin) * SUBPIXEL_STEPS).floor() as u8;
+
+        if self.glyphs.len() >= CACHE_LIMIT {
+            self.glyphs.clear();
+        }
+
+        let font = &self.faces[glyph.face];
+        let raster = self.glyphs
+            .entry(GlyphKey { glyph, size: size.to_bits(), subpixel })
+            .or_insert_with(|| {
+                let offset = subpixel as f32 / SUBPIXEL_STEPS;
+                let outline = font.outline_glyph(glyph.id.with_scale_and_position(size, point(offset, 0.0)))?;
+
+                let bounds = outline.px_bounds();
+                let width = bounds.width() as u32;
+                let height = bounds.height() as u32;
+                let mut coverage = vec![0; (width * height) as usize];
+
+                outline.draw(|x, y, v| {
+                    coverage[(y * width + x) as usize] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                });
+
+                Some(RasterGlyph {
+                    left: bounds.min.x as i32,
+                    top: bounds.min.y as i32,
+                    width, height, coverage
+                })
+            });
+
+        (origin as i32, raster.as_ref())
+    }
+}
+
+fn load_face(path: &str) -> Result<FontVec, FontError> {
+    let data = std::fs::read(path)
+        .map_err(|why| FontError::Read { path: path.to_string(), why })?;
+
+    FontVec::try_from_vec(data)
+        .map_err(|_| FontError::Parse { path: path.to_string() })
+}
+
+#[test]
+fn missing_font_is_an_error() {
+    let error = FontState::load(&["/nonexistent/font.otf".into()]).err().unwrap();
+    assert!(error.to_string().starts_with("failed to read font `/nonexistent/font.otf`"));
+}
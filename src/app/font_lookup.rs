@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use ttf_parser::{Face, name_id};
+
+const EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// Families tried for the generic names when fontconfig isn't around to resolve them
+const GENERIC: &[(&str, &[&str])] = &[
+    ("sans-serif", &["DejaVu Sans", "Noto Sans", "Liberation Sans", "Cantarell"]),
+    ("serif",      &["DejaVu Serif", "Noto Serif", "Liberation Serif", "C059"]),
+    ("monospace",  &["DejaVu Sans Mono", "Noto Sans Mono", "Liberation Mono", "JetBrains Mono"]),
+];
+
+/// Finds font files by family name, through `fc-match` when fontconfig is installed
+/// and by scanning the usual font directories otherwise
+#[derive(Default)]
+pub struct FontLookup {
+    /// Filled on the first lookup fontconfig couldn't answer
+    files: Option<Vec<PathBuf>>,
+}
+
+impl FontLookup {
+    pub fn find(&mut self, family: &str) -> Option<PathBuf> {
+        if family.contains('/') {
+            return Some(PathBuf::from(family));
+        }
+
+        if let Some(path) = fc_match(family) {
+            return Some(path);
+        }
+
+        let generic = GENERIC.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(family))
+            .map(|(_, families)| *families);
+
+        match generic {
+            Some(families) => families.iter().find_map(|family| self.scan(family)),
+            None => self.scan(family),
+        }
+    }
+
+    fn scan(&mut self, family: &str) -> Option<PathBuf> {
+        let files = self.files.get_or_insert_with(font_files);
+        let wanted = normalize(family);
+
+        // reading every font on the system is slow, so files named like the family go first
+        let (likely, rest): (Vec<&PathBuf>, Vec<&PathBuf>) = files.iter().partition(|path| {
+            path.file_stem()
+                .map(|stem| normalize(&stem.to_string_lossy()).starts_with(&wanted))
+                .unwrap_or(false)
+        });
+
+        best_match(&likely, family).or_else(|| best_match(&rest, family))
+    }
+}
+
+/// The most regular looking face of `family` among `paths`
+fn best_match(paths: &[&PathBuf], family: &str) -> Option<PathBuf> {
+    paths.iter()
+        .filter_map(|path| {
+            let data = std::fs::read(path).ok()?;
+            let face = Face::parse(&data, 0).ok()?;
+
+            let mut matches = false;
+            let mut regular = false;
+
+            for name in face.names() {
+                let Some(text) = name.to_string() else { continue };
+
+                match name.name_id {
+                    name_id::FAMILY | name_id::TYPOGRAPHIC_FAMILY => {
+                        matches |= text.eq_ignore_ascii_case(family);
+                    }
+                    name_id::SUBFAMILY => {
+                        regular |= matches!(text.to_ascii_lowercase().as_str(), "regular" | "book" | "roman");
+                    }
+                    _ => {}
+                }
+            }
+
+            matches.then(|| (!regular, path.as_os_str().len(), (*path).clone()))
+        })
+        .min()
+        .map(|(_, _, path)| path)
+}
+
+/// `fc-match` always answers with something, so its pick only counts if the family is right
+fn fc_match(family: &str) -> Option<PathBuf> {
+    let output = Command::new("fc-match")
+        .arg("--format=%{family}\n%{file}")
+        .arg(format!("{family}:style=Regular"))
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8(output.stdout).ok()?;
+    let (families, file) = output.split_once('\n')?;
+
+    let generic = GENERIC.iter().any(|(name, _)| name.eq_ignore_ascii_case(family));
+    let found = families.split(',').any(|f| f.trim().eq_ignore_ascii_case(family));
+
+    (generic || found).then(|| PathBuf::from(file))
+}
+
+fn font_directories() -> Vec<PathBuf> {
+    let home = std::env::var("HOME").unwrap_or_default();
+
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .unwrap_or_else(|_| format!("{home}/.local/share"));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".into());
+
+    let mut directories = vec![
+        PathBuf::from(data_home).join("fonts"),
+        PathBuf::from(home).join(".fonts"),
+    ];
+    directories.extend(data_dirs.split(':').map(|dir| Path::new(dir).join("fonts")));
+
+    directories
+}
+
+fn font_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = font_directories();
+
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else { continue };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            // symlinked directories are skipped so a link loop can't keep this going forever
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push(path);
+            }
+            else if path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,200 @@
+use std::time::Instant;
+
+use smithay_client_toolkit::seat::pointer::PointerEventKind;
+
+use super::config::{Corner, Edge, HotConfig, HotRegion};
+
+impl HotRegion {
+    /// Whether `(x, y)` on a bar `width` by `height` is in the region, which reaches `depth` into it
+    pub fn contains(&self, depth: f64, (x, y): (f64, f64), (width, height): (f64, f64)) -> bool {
+        let (left, top) = (x < depth, y < depth);
+        let (right, bottom) = (x >= width - depth, y >= height - depth);
+
+        match *self {
+            HotRegion::Corner(Corner::TopLeft) => top && left,
+            HotRegion::Corner(Corner::TopRight) => top && right,
+            HotRegion::Corner(Corner::BottomLeft) => bottom && left,
+            HotRegion::Corner(Corner::BottomRight) => bottom && right,
+            HotRegion::Edge { edge, from, to } => {
+                let (on_edge, along, length) = match edge {
+                    Edge::Top => (top, x, width),
+                    Edge::Bottom => (bottom, x, width),
+                    Edge::Left => (left, y, height),
+                    Edge::Right => (right, y, height),
+                };
+                on_edge && along >= from as f64 * length && along <= to as f64 * length
+            }
+        }
+    }
+}
+
+/// Where the pointer is among the hot regions of one bar
+#[derive(Default)]
+pub struct HotState {
+    /// For every configured region, when the pointer came in and whether the action ran since.
+    /// None while the pointer is outside.
+    inside: Vec<Option<(Instant, bool)>>,
+    /// When each region's action last ran, kept when the pointer leaves the bar
+    last_run: Vec<Option<Instant>>,
+}
+
+impl HotState {
+    /// Follows a pointer event at `position` on a bar `size` big, in logical pixels.
+    /// Returns the indices of the regions whose action is due.
+    pub fn pointer(&mut self, regions: &[HotConfig], kind: &PointerEventKind, position: (f64, f64), size: (f64, f64), now: Instant) -> Vec<usize> {
+        match kind {
+            PointerEventKind::Enter { .. } | PointerEventKind::Motion { .. } => self.motion(regions, position, size, now),
+            PointerEventKind::Leave { .. } => {
+                self.leave();
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Moves the pointer to `position` on a bar `size` big, in logical pixels.
+    /// Returns the indices of the regions whose action is due.
+    pub fn motion(&mut self, regions: &[HotConfig], position: (f64, f64), size: (f64, f64), now: Instant) -> Vec<usize> {
+        self.inside.resize(regions.len(), None);
+        self.last_run.resize(regions.len(), None);
+
+        for ((hot, inside), last_run) in regions.iter().zip(self.inside.iter_mut()).zip(self.last_run.iter()) {
+            if !hot.region.contains(hot.size as f64, position, size) {
+                *inside = None;
+            }
+            else if inside.is_none() {
+                // coming back too soon uses up the visit, the pointer has to leave again
+                let cooling_down = last_run.is_some_and(|last| now < last + hot.cooldown);
+                *inside = Some((now, cooling_down));
+            }
+        }
+
+        self.tick(regions, now)
+    }
+
+    /// The pointer left the bar, which leaves every region
+    pub fn leave(&mut self) {
+        self.inside.clear();
+    }
+
+    /// When the next dwell time runs out, if the pointer is waiting in a region
+    pub fn deadline(&self, regions: &[HotConfig]) -> Option<Instant> {
+        regions.iter().zip(self.inside.iter())
+            .filter_map(|(hot, inside)| match inside {
+                Some((since, false)) => Some(*since + hot.dwell),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Returns the indices of the regions the pointer stayed in long enough, once per visit
+    pub fn tick(&mut self, regions: &[HotConfig], now: Instant) -> Vec<usize> {
+        let mut due = Vec::new();
+        self.last_run.resize(regions.len(), None);
+
+        for (index, (hot, inside)) in regions.iter().zip(self.inside.iter_mut()).enumerate() {
+            if let Some((since, fired)) = inside && !*fired && *since + hot.dwell <= now {
+                *fired = true;
+                self.last_run[index] = Some(now);
+                due.push(index);
+            }
+        }
+
+        due
+    }
+}
+
+#[test]
+fn regions_fire_after_dwelling() {
+    use std::time::Duration;
+    use super::config::Action;
+
+    let corner = HotConfig {
+        name: "corner".into(),
+        region: HotRegion::Corner(Corner::BottomLeft),
+        size: 2,
+        dwell: Duration::from_millis(200),
+        cooldown: Duration::ZERO,
+        action: Action::TogglePin,
+    };
+    let edge = HotConfig {
+        name: "edge".into(),
+        region: HotRegion::Edge { edge: Edge::Top, from: 0.5, to: 1.0 },
+        size: 1,
+        dwell: Duration::ZERO,
+        cooldown: Duration::ZERO,
+        action: Action::Reload,
+    };
+    let regions = [corner, edge];
+    let size = (1000.0, 24.0);
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut state = HotState::default();
+
+    assert_eq!(state.motion(&regions, (1.0, 22.5), size, at(0)), Vec::<usize>::new());
+    assert_eq!(state.deadline(&regions), Some(at(200)));
+    assert_eq!(state.tick(&regions, at(200)), vec![0]);
+    // once per visit
+    assert_eq!(state.motion(&regions, (0.0, 23.0), size, at(500)), Vec::<usize>::new());
+    assert_eq!(state.deadline(&regions), None);
+
+    // leaving before the dwell time is up doesn't run it
+    state.pointer(&regions, &PointerEventKind::Leave { serial: 0 }, (0.0, 0.0), size, at(550));
+    state.motion(&regions, (1.0, 22.5), size, at(600));
+    state.motion(&regions, (10.0, 10.0), size, at(700));
+    assert_eq!(state.tick(&regions, at(900)), Vec::<usize>::new());
+
+    assert_eq!(state.motion(&regions, (400.0, 0.0), size, at(1000)), Vec::<usize>::new());
+    assert_eq!(state.motion(&regions, (600.0, 0.0), size, at(1000)), vec![1]);
+}
+
+#[test]
+fn corners_need_leaving_and_a_cooldown() {
+    use std::time::Duration;
+    use super::config::Action;
+    use PointerEventKind::*;
+
+    let corner = HotConfig {
+        name: "overview".into(),
+        region: HotRegion::Corner(Corner::BottomLeft),
+        size: 1,
+        dwell: Duration::ZERO,
+        cooldown: Duration::from_millis(500),
+        action: Action::Command("niri msg action open-overview".into()),
+    };
+    let regions = [corner];
+    let size = (1000.0, 24.0);
+    let start = Instant::now();
+    let mut state = HotState::default();
+
+    // events as they come in a pointer frame, with when they came
+    let mut feed = |events: &[(PointerEventKind, (f64, f64), u64)]| -> Vec<usize> {
+        events.iter()
+            .flat_map(|(kind, position, ms)| state.pointer(&regions, kind, *position, size, start + Duration::from_millis(*ms)))
+            .collect()
+    };
+    let corner = (0.0, 23.5);
+
+    // the pointer resting against the corner keeps sending motion events
+    let mut resting = vec![(Enter { serial: 0 }, (5.0, 20.0), 0)];
+    resting.extend((1..20).map(|ms| (Motion { time: ms as u32 }, corner, ms)));
+    assert_eq!(feed(&resting), vec![0]);
+
+    // jittering out and back in doesn't run it again
+    assert_eq!(feed(&[
+        (Motion { time: 100 }, (3.0, 20.0), 100),
+        (Motion { time: 150 }, corner, 150),
+    ]), Vec::<usize>::new());
+
+    // neither does leaving the bar, the cooldown outlasts it
+    assert_eq!(feed(&[
+        (Leave { serial: 1 }, corner, 300),
+        (Enter { serial: 2 }, corner, 400),
+    ]), Vec::<usize>::new());
+
+    // out, and back after the cooldown
+    assert_eq!(feed(&[
+        (Leave { serial: 3 }, corner, 500),
+        (Enter { serial: 4 }, corner, 600),
+    ]), vec![0]);
+}
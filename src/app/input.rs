@@ -1,68 +1,234 @@
-use super::BarWindow;
+use std::time::Instant;
 
 use smithay_client_toolkit::{
-    seat::pointer::{PointerEvent, PointerEventKind::*}, 
-    shell::WaylandSurface
+    reexports::calloop::timer::{TimeoutAction, Timer},
+    seat::pointer::{AxisScroll, PointerEvent, PointerEventKind::*},
+    shell::WaylandSurface,
 };
 
+use super::BarWindow;
+use crate::modules::Trigger;
+
+use super::config::{Action, ModuleConfig};
+use super::visibility::Pointer;
+
 impl BarWindow {
     pub (super) fn handle_input_event(&mut self, events: &[PointerEvent]) {
+        let now = Instant::now();
+        let mut changed = vec![false; self.bars.len()];
+        let mut hot_changed = vec![false; self.bars.len()];
+        // (bar, module, action), ran once the bars are done with the events
+        let mut actions: Vec<(usize, Option<String>, Action)> = Vec::new();
+
         for event in events {
-            if &event.surface != self.wayland.surface.wl_surface() {
-                continue;
+            let Some(index) = self.bar_index(&event.surface) else { continue };
+            let bar = &mut self.bars[index];
+
+            // along the screen edge, and away from it
+            let (x, y) = bar.edge_position(self.config.bar.edge, event.position);
+
+            let size = (bar.width as f64, bar.height as f64);
+            let due = bar.hot.pointer(&self.config.hot, &event.kind, event.position, size, now);
+            actions.extend(due.into_iter().map(|hot| (index, None, self.config.hot[hot].action.clone())));
+            if matches!(event.kind, Enter { .. } | Leave { .. } | Motion { .. }) {
+                hot_changed[index] = true;
             }
 
-            match event.kind {
-                Press { button, .. } => if button == 273 {
-                    self.state.no_disappearing = !self.state.no_disappearing;
+            let pointer = match event.kind {
+                Press { button, .. } => {
+                    let scale = bar.scale_factor() as f64;
+                    let position = ((event.position.0 * scale) as i32, (event.position.1 * scale) as i32);
+                    let module = bar.hitboxes.module_at(position);
 
-                    if event.position.1 == 0.0 && event.position.0 > 0.0 {
-                        self.state.bar_width = event.position.0 as u32;
+                    let trigger = match button {
+                        BTN_LEFT => Some(Trigger::LeftClick),
+                        BTN_RIGHT => Some(Trigger::RightClick),
+                        BTN_MIDDLE => Some(Trigger::MiddleClick),
+                        _ => None,
+                    };
+                    let bound = trigger.and_then(|trigger| binding(&self.config.modules, module?, trigger));
+
+                    // a span's own action is more specific than its module's
+                    if button == BTN_LEFT && let Some(command) = bar.hitboxes.action_at(position) {
+                        actions.push((index, None, Action::Command(command.to_string())));
+                        None
+                    }
+                    else if let Some(action) = bound {
+                        actions.push((index, module.map(str::to_string), action));
+                        None
+                    }
+                    else if let Some(module) = module && let Some(trigger) = trigger && self.state.module_input(module, trigger) {
+                        actions.push((index, Some(module.to_string()), Action::Refresh));
+                        None
+                    }
+                    else if button == BTN_RIGHT {
+                        Some(Pointer::TogglePin)
                     }
-                    else if event.position.0 >= 0.0 || event.position.1 >= 0.0 {
-                        self.state.bar_width = 0;
+                    else {
+                        None
                     }
                 }
 
-                Leave { .. } => if !self.state.no_disappearing {
-                    self.state.bar_width = 0;
-                    self.state.track_x = false;
+                Axis { vertical, .. } => {
+                    let scale = bar.scale_factor() as f64;
+                    let position = ((event.position.0 * scale) as i32, (event.position.1 * scale) as i32);
+                    let module = bar.hitboxes.module_at(position);
 
-                    let x = event.position.0;
-                    let y = event.position.1;
+                    let steps = scroll_steps(&mut bar.scroll, &vertical);
+                    let trigger = if steps < 0 { Trigger::ScrollUp } else { Trigger::ScrollDown };
 
-                    if x == 0.0 && self.state.track_y && y > self.graphics.height as f64 - 1.5 {
-                        self.state.execute_command("niri msg action open-overview");
+                    if let Some(module) = module && let Some(action) = binding(&self.config.modules, module, trigger) {
+                        for _ in 0..steps.abs() {
+                            actions.push((index, Some(module.to_string()), action.clone()));
+                        }
                     }
+                    else if let Some(module) = module && steps != 0 {
+                        let handled = (0..steps.abs()).fold(false, |handled, _| self.state.module_input(module, trigger) || handled);
+                        if handled {
+                            actions.push((index, Some(module.to_string()), Action::Refresh));
+                        }
+                    }
+                    None
+                }
 
-                    self.state.track_y = false;
+                Leave { .. } => {
+                    bar.scroll = 0.0;
+                    Some(Pointer::Leave)
                 }
 
-                Motion { .. } => if !self.state.no_disappearing {
-                    let x = event.position.0;
-                    let y = event.position.1;
+                Motion { .. } => Some(Pointer::Motion { along: x, across: y }),
+                _ => None,
+            };
 
-                    if event.position == (0.0, 0.0) {
-                        self.state.track_x = true;
-                        self.state.track_y = true;
-                    }
-                    else if x > 0.0 && y > 0.0 {
-                        self.state.track_x = false;
-                        self.state.track_y = false;
-                    }
+            if let Some(pointer) = pointer && bar.reveal.handle(&self.config.reveal, pointer, now) {
+                changed[index] = true;
+            }
+        }
 
-                    if y == 0.0 && x > 0.0 && self.state.track_x {
-                        self.state.bar_width = x as u32;
-                    }
-                    else if x == 0.0 && self.state.track_y && y > self.graphics.height as f64 - 1.5{
-                        self.state.execute_command("niri msg action open-overview");
-                    }
-                    else if x > 0.0 {
-                        self.state.bar_width = 0;
+        for (index, changed) in changed.into_iter().enumerate() {
+            if changed {
+                self.reveal_changed(index);
+            }
+        }
+        for (index, changed) in hot_changed.into_iter().enumerate() {
+            if changed {
+                self.schedule_hot(index);
+            }
+        }
+        for (index, module, action) in actions {
+            self.run_action(index, module.as_deref(), action);
+        }
+    }
+
+    /// Sets up a timer for when the pointer dwelled long enough in a hot region of the bar
+    fn schedule_hot(&mut self, index: usize) {
+        let bar = &mut self.bars[index];
+
+        if let Some(timer) = bar.hot_timer.take() {
+            self.state.loop_handle.remove(timer);
+        }
+
+        let Some(deadline) = bar.hot.deadline(&self.config.hot) else { return };
+        let surface = bar.surface.wl_surface().clone();
+
+        bar.hot_timer = self.state.loop_handle
+            .insert_source(Timer::from_deadline(deadline), move |_, _, window| {
+                if let Some(index) = window.bar_index(&surface) {
+                    window.bars[index].hot_timer = None;
+
+                    let due = window.bars[index].hot.tick(&window.config.hot, Instant::now());
+                    window.schedule_hot(index);
+
+                    for hot in due {
+                        let action = window.config.hot[hot].action.clone();
+                        window.run_action(index, None, action);
                     }
                 }
-                _ => {}
+                TimeoutAction::Drop
+            })
+            .inspect_err(|why| eprintln!("Failed to set a hot corner timer: {why}"))
+            .ok();
+    }
+
+    /// Runs `action` for bar `index`, `module` is the one it's bound to if any
+    pub (super) fn run_action(&mut self, index: usize, module: Option<&str>, action: Action) {
+        match action {
+            Action::Command(command) => self.state.launcher.spawn(&command),
+            Action::TogglePin => {
+                if self.bars[index].reveal.handle(&self.config.reveal, Pointer::TogglePin, Instant::now()) {
+                    self.reveal_changed(index);
+                }
+            }
+            Action::Reload => self.reload_config(),
+            Action::Refresh => {
+                let ids: Vec<u64> = self.state.modules.iter()
+                    .filter(|slot| module.is_none_or(|name| slot.config.name == name))
+                    .map(|slot| slot.id)
+                    .collect();
+
+                ids.into_iter().for_each(|id| { self.refresh_module(id); });
             }
         }
     }
+
+    /// Redraws the bar and sets up a timer for its next reveal timeout, if it has one
+    pub fn reveal_changed(&mut self, index: usize) {
+        let bar = &mut self.bars[index];
+
+        if let Some(timer) = bar.reveal_timer.take() {
+            self.state.loop_handle.remove(timer);
+        }
+
+        if let Some(deadline) = bar.reveal.deadline(&self.config.reveal) {
+            let surface = bar.surface.wl_surface().clone();
+
+            bar.reveal_timer = self.state.loop_handle
+                .insert_source(Timer::from_deadline(deadline), move |_, _, window| {
+                    // the bar may be gone by now
+                    if let Some(index) = window.bar_index(&surface) {
+                        let bar = &mut window.bars[index];
+                        bar.reveal_timer = None;
+
+                        if bar.reveal.tick(&window.config.reveal, Instant::now()) {
+                            window.reveal_changed(index);
+                        }
+                    }
+                    TimeoutAction::Drop
+                })
+                .inspect_err(|why| eprintln!("Failed to set a reveal timer: {why}"))
+                .ok();
+        }
+
+        self.request_bar_redraw(index);
+    }
+}
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+fn binding(modules: &[ModuleConfig], module: &str, trigger: Trigger) -> Option<Action> {
+    modules.iter()
+        .find(|config| config.name == module)?
+        .bindings.iter()
+        .find(|(bound, _)| *bound == trigger)
+        .map(|(_, action)| action.clone())
+}
+
+/// Whole scroll steps, negative ones are up. What's left of a touchpad's smooth scrolling is kept in `pending`.
+fn scroll_steps(pending: &mut f64, scroll: &AxisScroll) -> i32 {
+    *pending += if scroll.value120 != 0 {
+        scroll.value120 as f64 / 120.0
+    }
+    else if scroll.discrete != 0 {
+        scroll.discrete as f64
+    }
+    else {
+        // about what a wheel click scrolls
+        scroll.absolute / 10.0
+    };
+
+    let steps = pending.trunc();
+    *pending = if scroll.stop { 0.0 } else { *pending - steps };
+    steps as i32
 }
@@ -0,0 +1,63 @@
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+
+use smithay_client_toolkit::reexports::calloop::{
+    signals::{Signal, Signals},
+    LoopHandle,
+};
+
+use super::BarWindow;
+
+/// Commands started by actions, kept until they exit so they don't linger as zombies
+#[derive(Default)]
+pub struct Launcher {
+    children: Vec<(String, Child)>,
+}
+
+impl Launcher {
+    /// Runs `command` with `sh -c` without waiting for it, so quoting, pipes and `$VARS` work like in a shell
+    pub fn spawn(&mut self, command: &str) {
+        if command.trim().is_empty() {
+            return;
+        }
+
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            // its own process group, ctrl-c on the bar doesn't take it down too
+            .process_group(0)
+            .spawn();
+
+        match child {
+            Ok(child) => self.children.push((command.to_string(), child)),
+            Err(why) => eprintln!("Failed to run `{command}`: {why}"),
+        }
+    }
+
+    /// Collects the children that exited, the ones that failed are logged
+    pub fn reap(&mut self) {
+        self.children.retain_mut(|(command, child)| match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                if !status.success() {
+                    eprintln!("`{command}` exited with {status}");
+                }
+                false
+            }
+            Err(why) => {
+                eprintln!("Failed to wait for `{command}`: {why}");
+                false
+            }
+        });
+    }
+}
+
+/// Reaps launched commands as they exit.
+/// Has to be set up before any threads are started, they'd get the SIGCHLD otherwise.
+pub fn reap_children(handle: &LoopHandle<'static, BarWindow>) {
+    let signals = Signals::new(&[Signal::SIGCHLD]).expect("Failed to block SIGCHLD");
+    handle
+        .insert_source(signals, |_, _, window| window.state.launcher.reap())
+        .expect("Failed to insert the SIGCHLD source");
+}
@@ -0,0 +1,183 @@
+use super::color::Color;
+
+/// How a span is drawn, unset fields are taken from the bar
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    /// Multiplies the foreground's alpha, to dim a part of the text
+    pub alpha: Option<f32>,
+    /// A family name or font file, the configured fonts are its fallback
+    pub font: Option<String>,
+    pub underline: bool,
+    /// Command ran when the span is clicked
+    pub action: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn plain(text: &str) -> Self {
+        Self { text: text.to_string(), style: Style::default() }
+    }
+}
+
+/// Where the tag `text` starts with ends, a `>` in a quoted attribute value doesn't end it
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(index),
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parses module output with `<span fg="…" bg="…" alpha="…" font="…" underline="true" action="…">` tags,
+/// which can be nested. `&lt;`, `&gt;`, `&amp;`, `&quot;` and `&apos;` stand for the characters themselves.
+pub fn parse(markup: &str) -> Result<Vec<Span>, String> {
+    let mut spans = Vec::new();
+    let mut styles = vec![Style::default()];
+    let mut text = String::new();
+    let mut rest = markup;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => {
+                let end = tag_end(rest).ok_or("`<` without a closing `>`, write `&lt;` for the character")?;
+                let tag = &rest[1..end];
+                rest = &rest[end + 1..];
+
+                if !text.is_empty() {
+                    spans.push(Span { text: std::mem::take(&mut text), style: styles.last().unwrap().clone() });
+                }
+
+                if tag.trim() == "/span" {
+                    if styles.len() == 1 {
+                        return Err("`</span>` without an open `<span>`".into());
+                    }
+                    styles.pop();
+                }
+                else if let Some(attributes) = tag.strip_prefix("span")
+                    && (attributes.is_empty() || attributes.starts_with(char::is_whitespace))
+                {
+                    let mut style = styles.last().unwrap().clone();
+                    apply_attributes(&mut style, attributes)?;
+                    styles.push(style);
+                }
+                else {
+                    return Err(format!("unknown tag `<{tag}>`, only `<span>` is supported"));
+                }
+            }
+            '&' => {
+                let end = rest.find(';').ok_or("`&` without a closing `;`, write `&amp;` for the character")?;
+                text.push(entity(&rest[1..end])?);
+                rest = &rest[end + 1..];
+            }
+            c => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if styles.len() > 1 {
+        return Err("`<span>` is never closed".into());
+    }
+    if !text.is_empty() {
+        spans.push(Span { text, style: Style::default() });
+    }
+
+    Ok(spans)
+}
+
+/// Like `parse`, but broken markup is reported and shown as it is
+pub fn parse_or_plain(markup: &str, source: &str) -> Vec<Span> {
+    parse(markup).unwrap_or_else(|why| {
+        eprintln!("{source}: {why}");
+        vec![Span::plain(markup)]
+    })
+}
+
+fn entity(name: &str) -> Result<char, String> {
+    match name {
+        "lt" => Ok('<'),
+        "gt" => Ok('>'),
+        "amp" => Ok('&'),
+        "quot" => Ok('"'),
+        "apos" => Ok('\''),
+        name => Err(format!("unknown entity `&{name};`")),
+    }
+}
+
+fn apply_attributes(style: &mut Style, mut attributes: &str) -> Result<(), String> {
+    loop {
+        attributes = attributes.trim_start();
+        if attributes.is_empty() {
+            return Ok(());
+        }
+
+        let (name, rest) = attributes.split_once('=')
+            .ok_or_else(|| format!("expected `name=\"value\"`, found `{attributes}`"))?;
+        let name = name.trim();
+        let rest = rest.trim_start();
+
+        let quote = rest.chars().next().filter(|q| *q == '"' || *q == '\'')
+            .ok_or_else(|| format!("the value of `{name}` has to be quoted"))?;
+        let end = rest[1..].find(quote)
+            .ok_or_else(|| format!("the value of `{name}` is never closed"))?;
+        let value = &rest[1..end + 1];
+        attributes = &rest[end + 2..];
+
+        match name {
+            "fg" | "foreground" => style.fg = Some(Color::parse(value)?),
+            "bg" | "background" => style.bg = Some(Color::parse(value)?),
+            "alpha" => style.alpha = Some(match value.parse::<f32>() {
+                Ok(alpha) if (0.0..=1.0).contains(&alpha) => alpha,
+                _ => return Err(format!("`{value}` is not an alpha value between 0 and 1")),
+            }),
+            "font" => style.font = Some(value.to_string()),
+            "underline" => style.underline = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("`underline` is `true` or `false`, found `{value}`")),
+            },
+            "action" => style.action = Some(value.to_string()),
+            name => return Err(format!("unknown attribute `{name}`")),
+        }
+    }
+}
+
+#[test]
+fn parse_nested_spans() {
+    let spans = parse("12 <span fg=\"red\" underline='true'>3<span alpha=\"0.5\">4</span></span> &lt;5&gt;").unwrap();
+    let red = Color::rgb(0xff, 0, 0);
+
+    assert_eq!(spans, vec![
+        Span::plain("12 "),
+        Span { text: "3".into(), style: Style { fg: Some(red), underline: true, ..Style::default() } },
+        Span { text: "4".into(), style: Style { fg: Some(red), underline: true, alpha: Some(0.5), ..Style::default() } },
+        Span::plain(" <5>"),
+    ]);
+
+    assert!(parse("<b>bold</b>").is_err());
+    assert!(parse("<span fg=\"red\">open").is_err());
+    assert!(parse("</span>").is_err());
+    assert!(parse("<span fg=red>x</span>").is_err());
+    assert!(parse("a & b").is_err());
+
+    // a `>` inside a quoted value is part of it
+    let spans = parse("<span fg=\"red\" action='echo \"a>b\" > /dev/null'>x</span>").unwrap();
+    assert_eq!(spans[0].text, "x");
+    assert_eq!(spans[0].style.action.as_deref(), Some("echo \"a>b\" > /dev/null"));
+    assert!(parse("<span action=\"a>b>x</span>").is_err());
+}
@@ -1,6 +1,30 @@
 mod application;
 pub use application::*;
 
+mod bar;
 mod drawing;
+mod render;
+pub use render::render_png;
+mod font;
+mod font_lookup;
+pub use font::FontState;
+mod color;
+mod markup;
 mod config;
+pub use config::{ConfigState, ModuleConfig};
+
+mod syntax;
+mod diagnostic;
 mod input;
+mod hot;
+mod visibility;
+mod animation;
+
+mod reload;
+mod launch;
+mod control;
+mod scale;
+mod schedule;
+pub use reload::watch_config;
+pub use launch::reap_children;
+pub use control::serve_control;
@@ -0,0 +1,71 @@
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use inotify::{Inotify, WatchMask};
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic,
+    signals::{Signal, Signals},
+    Interest, LoopHandle, Mode, PostAction,
+};
+
+use super::BarWindow;
+
+/// Reloads the config on SIGUSR1 and whenever the file at `path` is written or replaced
+pub fn watch_config(handle: &LoopHandle<'static, BarWindow>, path: &str) {
+    let signals = Signals::new(&[Signal::SIGUSR1]).expect("Failed to block SIGUSR1");
+    handle
+        .insert_source(signals, |_, _, window| window.reload_config())
+        .expect("Failed to insert the SIGUSR1 source");
+
+    if let Err(why) = watch_file(handle, Path::new(path)) {
+        eprintln!("Not watching {path} for changes: {why}");
+    }
+}
+
+fn watch_file(handle: &LoopHandle<'static, BarWindow>, path: &Path) -> std::io::Result<()> {
+    let inotify = Inotify::init()?;
+
+    let directory = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+    let name: OsString = path.file_name().unwrap_or_default().to_owned();
+
+    // editors tend to replace the file instead of writing to it, which only shows up on the directory
+    inotify.watches().add(&directory, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE)?;
+    // a symlinked config (dotfile repos) is only noticed through the file itself
+    watch_target(&inotify, path);
+
+    let path: PathBuf = path.to_path_buf();
+    let source = Generic::new(inotify, Interest::READ, Mode::Level);
+
+    handle
+        .insert_source(source, move |_, inotify, window| {
+            // SAFETY: the inotify instance is only read from, it's never closed or replaced
+            let inotify = unsafe { inotify.get_mut() };
+            let mut buffer = [0; 4096];
+
+            let events = match inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(why) if why.kind() == ErrorKind::WouldBlock => return Ok(PostAction::Continue),
+                Err(why) => return Err(why),
+            };
+
+            let changed = events
+                .filter(|event| event.name.is_none_or(|n| n == name))
+                .count() > 0;
+
+            if changed {
+                watch_target(inotify, &path);
+                window.reload_config();
+            }
+
+            Ok(PostAction::Continue)
+        })
+        .map_err(|why| std::io::Error::other(why.to_string()))?;
+
+    Ok(())
+}
+
+/// Replacing the file drops its watch, so this is repeated after every change
+fn watch_target(inotify: &Inotify, path: &Path) {
+    let _ = inotify.watches().add(path, WatchMask::CLOSE_WRITE);
+}
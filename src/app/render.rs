@@ -0,0 +1,755 @@
+use std::collections::HashMap;
+
+use super::color::Color;
+use super::config::{ConfigState, FontConfig};
+use super::font::FontState;
+use super::markup::{self, Span};
+
+/// Premultiplied Argb8888 pixels, the format of the bar's buffers
+pub struct Canvas<'a> {
+    pub pixels: &'a mut [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Everything that ends up on a bar, without the surface it's drawn for
+pub struct Frame<'a> {
+    pub config: &'a ConfigState,
+    /// Parsed output of the running modules, by name
+    pub modules: Vec<(&'a str, &'a [Span])>,
+    pub scale: f32,
+    /// How far along the edge the bar is shown, in pixels of the canvas
+    pub revealed: usize,
+    /// Applied to the whole bar, for fading it in and out
+    pub opacity: f32,
+}
+
+/// (x, y, width, height) in canvas pixels
+pub type Rect = (i32, i32, i32, i32);
+
+/// Where things ended up on the canvas, to find what the pointer is over
+#[derive(Default)]
+pub struct Hitboxes {
+    /// Spans with an `action`, and the command
+    pub actions: Vec<(Rect, String)>,
+    /// Every module that's drawn, by name
+    pub modules: Vec<(Rect, String)>,
+}
+
+impl Hitboxes {
+    pub fn action_at(&self, position: (i32, i32)) -> Option<&str> {
+        find(&self.actions, position)
+    }
+
+    pub fn module_at(&self, position: (i32, i32)) -> Option<&str> {
+        find(&self.modules, position)
+    }
+}
+
+fn find(boxes: &[(Rect, String)], (x, y): (i32, i32)) -> Option<&str> {
+    boxes.iter()
+        .find(|((bx, by, w, h), _)| x >= *bx && y >= *by && x < bx + w && y < by + h)
+        .map(|(_, name)| name.as_str())
+}
+
+/// Draws `frame` over the whole canvas, returns where the modules and clickable spans ended up
+pub fn render(canvas: &mut Canvas, fonts: &mut Fonts, frame: &Frame) -> Hitboxes {
+    let (width, height) = (canvas.width, canvas.height);
+    let scale = frame.scale;
+    let revealed = frame.revealed;
+    let mut hitboxes = Hitboxes::default();
+
+    let vertical = frame.config.bar.edge.is_vertical();
+    // text runs along the edge, modules of a vertical bar are stacked across it
+    let thickness = if vertical { width } else { height };
+
+    let background = frame.config.bar.background.as_argb();
+
+    canvas.pixels.chunks_exact_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        row.chunks_exact_mut(4).enumerate().for_each(|(x, chunk)| {
+            let along = if vertical { y } else { x };
+
+            let color: u32 = if along <= revealed {
+                background
+            }
+            else {
+                0x00 << 24
+            };
+
+            let array: &mut [u8; 4] = chunk.try_into().unwrap();
+            *array = color.to_le_bytes();
+        });
+    });
+
+    if fonts.main.is_some() {
+        let size = frame.config.font.size * scale;
+        let margin = 8.0 * scale;
+        let rotate = vertical && frame.config.bar.rotate_text;
+
+        // modules that are running, each zone's in the order they're shown
+        let names = frame.config.zones.each_ref().map(|zone| zone.modules.iter()
+            .filter(|name| frame.modules.iter().any(|(module, _)| module == name))
+            .map(String::as_str)
+            .collect::<Vec<_>>());
+
+        let zones = frame.config.zones.each_ref().map(|zone| ZoneText {
+            modules: zone.modules.iter()
+                .filter_map(|name| frame.modules.iter().find(|(module, _)| module == name))
+                .map(|(_, spans)| spans.to_vec())
+                .collect(),
+            // checked when the config was loaded
+            separator: markup::parse(&zone.separator).unwrap_or_default(),
+            spacing: zone.spacing * scale,
+            priority: zone.priority,
+        });
+
+        let runs = {
+            let fonts = &*fonts;
+            let measure = |span: &Span| fonts.text_width(span, size);
+
+            // rotated text is laid out as if the bar was horizontal
+            if vertical && !rotate {
+                // a module per line, the left zone at the top and the right one at the end
+                let font = fonts.main.as_ref().unwrap();
+                let line_height = font.line_height(size);
+                let line_baseline = font.baseline(size, line_height.round() as u32);
+                let room = thickness as f32 - 2.0 * margin;
+                let mut runs = Vec::new();
+
+                for (index, zone) in zones.iter().enumerate() {
+                    let height = line_height * zone.modules.len() as f32;
+                    let top = match index {
+                        0 => margin,
+                        1 => (revealed as f32 - height) / 2.0,
+                        _ => revealed as f32 - margin - height,
+                    };
+
+                    for (i, spans) in zone.modules.iter().enumerate() {
+                        let top = (top + line_height * i as f32) as i32;
+                        let spans = truncate(std::slice::from_ref(spans), 0.0, room, &measure).concat();
+                        let mut pen = (thickness as f32 - spans.iter().map(&measure).sum::<f32>()) / 2.0;
+
+                        for span in spans {
+                            let advance = measure(&span);
+                            runs.push(Run {
+                                span, pen,
+                                baseline: top + line_baseline,
+                                top, bottom: top + line_height.round() as i32,
+                                module: Some((index, i)),
+                            });
+                            pen += advance;
+                        }
+                    }
+                }
+
+                runs
+            }
+            else {
+                let baseline = fonts.main.as_ref().unwrap().baseline(size, thickness);
+
+                layout(&zones, revealed as f32, margin, &measure).into_iter()
+                    .map(|(span, pen, module)| Run { span, pen, baseline, top: 0, bottom: thickness as i32, module })
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let foreground = frame.config.bar.foreground;
+        let linear = frame.config.font.gamma_correct;
+        // turned clockwise, reading top to bottom
+        let to_screen = |u: i32, v: i32| if rotate { (width as i32 - 1 - v, u) } else { (u, v) };
+        // from the corners along and across the bar, the far ones exclusive
+        let screen_rect = |(u0, v0, u1, v1): (i32, i32, i32, i32)| -> Rect {
+            let (x0, y0) = to_screen(u0, v0);
+            let (x1, y1) = to_screen(u1 - 1, v1 - 1);
+            (x0.min(x1), y0.min(y1), (x1 - x0).abs() + 1, (y1 - y0).abs() + 1)
+        };
+        // blends over what's drawn already, text over the unrevealed part stays see-through
+        let put = |canvas: &mut [u8], (x, y): (i32, i32), color: Color, coverage: f32| {
+            if x >= 0 && y >= 0 && x < width as i32 && y < height as i32 {
+                let idx = ((y as u32 * width + x as u32) * 4) as usize;
+                let pixel: &mut [u8; 4] = (&mut canvas[idx..idx + 4]).try_into().unwrap();
+                *pixel = color.blend_over(u32::from_le_bytes(*pixel), coverage, linear).to_le_bytes();
+            }
+        };
+
+        // (zone, module) and the bounds drawn for it so far, along the bar and across it
+        let mut bounds: Vec<(Owner, (i32, i32, i32, i32))> = Vec::new();
+
+        for run in runs {
+            let style = &run.span.style;
+            let start = run.pen.round() as i32;
+            let end = (run.pen + fonts.text_width(&run.span, size)).round() as i32;
+
+            if let Some(module) = run.module {
+                match bounds.iter_mut().find(|(owner, _)| *owner == module) {
+                    Some((_, (u0, v0, u1, v1))) => {
+                        (*u0, *v0, *u1, *v1) = ((*u0).min(start), (*v0).min(run.top), (*u1).max(end), (*v1).max(run.bottom));
+                    }
+                    None => bounds.push((module, (start, run.top, end, run.bottom))),
+                }
+            }
+
+            if let Some(bg) = style.bg {
+                for u in start..end {
+                    for v in run.top..run.bottom {
+                        put(canvas.pixels, to_screen(u, v), bg, 1.0);
+                    }
+                }
+            }
+
+            if let Some(action) = &style.action {
+                hitboxes.actions.push((screen_rect((start, run.top, end, run.bottom)), action.clone()));
+            }
+
+            let color = {
+                let color = style.fg.unwrap_or(foreground);
+                Color { a: (color.a as f32 * style.alpha.unwrap_or(1.0)).round() as u8, ..color }
+            };
+
+            let font = fonts.get_mut(style.font.as_deref());
+            let mut pen_x = run.pen;
+
+            for c in run.span.text.chars() {
+                let id = font.glyph(c);
+                let (origin, glyph) = font.rasterize(id, size, pen_x);
+
+                if let Some(glyph) = glyph {
+                    for gy in 0..glyph.height {
+                        for gx in 0..glyph.width {
+                            let coverage = glyph.coverage[(gy * glyph.width + gx) as usize];
+                            let u = origin + glyph.left + gx as i32;
+                            let v = run.baseline + glyph.top + gy as i32;
+
+                            if coverage == 0 {
+                                continue;
+                            }
+
+                            put(canvas.pixels, to_screen(u, v), color, coverage as f32 / 255.0);
+                        }
+                    }
+                }
+
+                pen_x += font.advance(id, size);
+            }
+
+            if style.underline {
+                let below = (2.0 * scale).round() as i32;
+
+                for v in 0..scale.round().max(1.0) as i32 {
+                    for u in start..end {
+                        put(canvas.pixels, to_screen(u, run.baseline + below + v), color, 1.0);
+                    }
+                }
+            }
+        }
+
+        hitboxes.modules = bounds.into_iter()
+            .map(|((zone, module), bounds)| (screen_rect(bounds), names[zone][module].to_string()))
+            .collect();
+    }
+
+    if frame.opacity < 1.0 {
+        // premultiplied, so every channel scales alike
+        canvas.pixels.iter_mut().for_each(|c| *c = (*c as f32 * frame.opacity).round() as u8);
+    }
+
+    hitboxes
+}
+
+/// Renders the bar as it would look on an output at scale 1, fully shown,
+/// with `outputs` the markup each module printed, by name
+pub fn render_png(config: &ConfigState, outputs: &[(String, String)], path: &str) -> Result<(), String> {
+    let spans: Vec<(&str, Vec<Span>)> = outputs.iter()
+        .map(|(name, output)| (name.as_str(), markup::parse_or_plain(output, &format!("module `{name}`"))))
+        .collect();
+
+    let mut fonts = Fonts::default();
+    fonts.load(&config.font);
+    fonts.load_span_fonts(config, spans.iter().map(|(_, spans)| spans.as_slice()));
+
+    let length = config.bar.width.unwrap_or(1920);
+    let (width, height) = match config.bar.edge.is_vertical() {
+        true => (config.bar.height, length),
+        false => (length, config.bar.height),
+    };
+
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    let mut canvas = Canvas { pixels: &mut pixels, width, height };
+    let frame = Frame {
+        config,
+        modules: spans.iter().map(|(name, spans)| (*name, spans.as_slice())).collect(),
+        scale: 1.0,
+        revealed: length as usize,
+        opacity: 1.0,
+    };
+
+    render(&mut canvas, &mut fonts, &frame);
+    canvas.write_png(path)
+}
+
+impl Canvas<'_> {
+    /// Straight alpha RGBA, the way image files store it
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels.chunks_exact(4)
+            .flat_map(|pixel| {
+                let [b, g, r, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                let straighten = |c: u8| if a == 0 { 0 } else { ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8 };
+                [straighten(r), straighten(g), straighten(b), a]
+            })
+            .collect()
+    }
+
+    pub fn write_png(&self, path: &str) -> Result<(), String> {
+        let file = std::fs::File::create(path).map_err(|why| format!("failed to create `{path}`: {why}"))?;
+
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.to_rgba()))
+            .map_err(|why| format!("failed to write `{path}`: {why}"))
+    }
+}
+
+#[derive(Default)]
+pub struct Fonts {
+    /// None when the configured font couldn't be loaded, only the background is drawn then
+    pub main: Option<FontState>,
+    /// Fonts asked for by `<span font="…">`, with the configured ones as fallback.
+    /// None if they couldn't be loaded, so they're not looked up on every frame.
+    pub spans: HashMap<String, Option<FontState>>,
+}
+
+impl Fonts {
+    /// Loads the configured fonts unless they're already loaded.
+    /// If none of them can be loaded the previous fonts are kept, if there are any.
+    pub fn load(&mut self, config: &FontConfig) {
+        if self.main.as_ref().is_some_and(|font| font.families == config.families) {
+            return;
+        }
+
+        match FontState::load(&config.families) {
+            Ok(font) => {
+                font.missing.iter().for_each(|why| eprintln!("{why}"));
+                self.main = Some(font);
+                self.spans.clear();
+            }
+            Err(why) => eprintln!("{why}"),
+        }
+    }
+
+    /// Loads the fonts `<span font="…">` asks for in the modules' output and the separators.
+    /// Looking them up can take a while, so it's done when they change and `render` only uses what's loaded.
+    pub fn load_span_fonts<'a>(&mut self, config: &ConfigState, modules: impl IntoIterator<Item = &'a [Span]>) {
+        let separators: Vec<Span> = config.zones.iter()
+            // checked when the config was loaded
+            .flat_map(|zone| markup::parse(&zone.separator).unwrap_or_default())
+            .collect();
+
+        let families: Vec<String> = modules.into_iter().flatten()
+            .filter_map(|span| span.style.font.clone())
+            .chain(separators.into_iter().filter_map(|span| span.style.font))
+            .collect();
+        families.iter().for_each(|family| self.load_span_font(family, &config.font));
+    }
+
+    fn load_span_font(&mut self, family: &str, config: &FontConfig) {
+        if self.spans.contains_key(family) {
+            return;
+        }
+
+        let families: Vec<String> = std::iter::once(family.to_string())
+            .chain(config.families.iter().cloned())
+            .collect();
+
+        // the fallbacks are the bar's fonts, so anything missing beyond what they lack is `family`
+        let lacking = self.main.as_ref().map_or(0, |font| font.missing.len());
+
+        let font = match FontState::load(&families) {
+            Ok(font) if font.missing.len() == lacking => Some(font),
+            Ok(font) => {
+                eprintln!("{}", font.missing[0]);
+                None
+            }
+            Err(why) => {
+                eprintln!("{why}");
+                None
+            }
+        };
+
+        self.spans.insert(family.to_string(), font);
+    }
+
+    /// The span's font if it was loaded, otherwise the bar's font, which has to be there
+    pub fn get_mut(&mut self, family: Option<&str>) -> &mut FontState {
+        match family.and_then(|family| self.spans.get_mut(family)).and_then(Option::as_mut) {
+            Some(font) => font,
+            None => self.main.as_mut().unwrap(),
+        }
+    }
+
+    pub fn text_width(&self, span: &Span, size: f32) -> f32 {
+        let font = span.style.font.as_deref()
+            .and_then(|family| self.spans.get(family))
+            .and_then(Option::as_ref)
+            .or(self.main.as_ref());
+
+        font.map_or(0.0, |font| span.text.chars().map(|c| font.advance(font.glyph(c), size)).sum())
+    }
+}
+
+const ELLIPSIS: &str = "…";
+
+/// What a zone shows, with sizes in physical pixels
+pub struct ZoneText {
+    pub modules: Vec<Vec<Span>>,
+    pub separator: Vec<Span>,
+    pub spacing: f32,
+    pub priority: i64,
+}
+
+impl ZoneText {
+    /// Module outputs with the separators between them
+    fn items(&self) -> Vec<Vec<Span>> {
+        let mut items = Vec::new();
+
+        for (i, module) in self.modules.iter().enumerate() {
+            if i > 0 && !self.separator.is_empty() {
+                items.push(self.separator.clone());
+            }
+            items.push(module.clone());
+        }
+
+        items
+    }
+
+    /// Which module each of `items` belongs to, None for separators
+    fn owners(&self) -> Vec<Option<usize>> {
+        let mut owners = Vec::new();
+
+        for i in 0..self.modules.len() {
+            if i > 0 && !self.separator.is_empty() {
+                owners.push(None);
+            }
+            owners.push(Some(i));
+        }
+
+        owners
+    }
+}
+
+/// A span placed on the bar, `top` and `bottom` bound its background across the bar
+struct Run {
+    span: Span,
+    pen: f32,
+    baseline: i32,
+    top: i32,
+    bottom: i32,
+    /// The module the span is part of, None for separators
+    module: Option<Owner>,
+}
+
+/// (zone, module), a module by its place in `ZoneText::modules`
+type Owner = (usize, usize);
+
+/// Places the left, center and right zones on a bar `length` pixels long, keeping `margin`
+/// to the ends and between zones. Zones that don't fit are cut short lowest priority first.
+/// Returns the spans with the position their pen starts at, and the (zone, module) they're from.
+fn layout(
+    zones: &[ZoneText; 3],
+    length: f32,
+    margin: f32,
+    measure: &impl Fn(&Span) -> f32,
+) -> Vec<(Span, f32, Option<Owner>)> {
+    let mut items = zones.each_ref().map(ZoneText::items);
+
+    let mut by_priority = [0, 1, 2];
+    by_priority.sort_by_key(|&zone| zones[zone].priority);
+
+    let widths = |items: &[Vec<Vec<Span>>; 3]| -> [f32; 3] {
+        [0, 1, 2].map(|zone| items_width(&items[zone], zones[zone].spacing, measure))
+    };
+
+    loop {
+        let width = widths(&items);
+        let shown = items.iter().filter(|items| !items.is_empty()).count();
+        let needed: f32 = width.iter().sum::<f32>() + margin * shown.saturating_sub(1) as f32;
+        let overflow = needed - (length - 2.0 * margin);
+
+        let Some(zone) = by_priority.into_iter().find(|&zone| !items[zone].is_empty()) else { break };
+
+        if overflow <= 0.0 {
+            break;
+        }
+
+        items[zone] = truncate(&items[zone], zones[zone].spacing, width[zone] - overflow, measure);
+    }
+
+    let width = widths(&items);
+
+    let left = margin;
+    let right = length - margin - width[2];
+    let center = ((length - width[1]) / 2.0)
+        .max(if items[0].is_empty() { margin } else { left + width[0] + margin })
+        .min(if items[2].is_empty() { length - margin } else { right - margin } - width[1]);
+
+    let mut runs = Vec::new();
+
+    for (zone, start) in [left, center, right].into_iter().enumerate() {
+        let mut pen = start;
+
+        // cutting items short keeps the first ones, so they still line up with their owners
+        for (item, owner) in std::mem::take(&mut items[zone]).into_iter().zip(zones[zone].owners()) {
+            for span in item {
+                let advance = measure(&span);
+                runs.push((span, pen, owner.map(|module| (zone, module))));
+                pen += advance;
+            }
+            pen += zones[zone].spacing;
+        }
+    }
+
+    runs
+}
+
+fn items_width(items: &[Vec<Span>], spacing: f32, measure: &impl Fn(&Span) -> f32) -> f32 {
+    let text: f32 = items.iter().flatten().map(measure).sum();
+    text + spacing * items.len().saturating_sub(1) as f32
+}
+
+/// Keeps as many of `items` as fit in `max`, ending with an ellipsis if something was cut
+fn truncate(items: &[Vec<Span>], spacing: f32, max: f32, measure: &impl Fn(&Span) -> f32) -> Vec<Vec<Span>> {
+    let mut kept = Vec::new();
+    let mut used = 0.0;
+
+    for item in items {
+        let gap = if kept.is_empty() { 0.0 } else { spacing };
+        let width: f32 = item.iter().map(measure).sum();
+
+        if used + gap + width <= max {
+            kept.push(item.clone());
+            used += gap + width;
+            continue;
+        }
+
+        used += gap;
+        let mut cut = Vec::new();
+
+        for span in item {
+            let ellipsis = measure(&Span { text: ELLIPSIS.into(), style: span.style.clone() });
+            let width = measure(span);
+
+            if used + width + ellipsis <= max {
+                cut.push(span.clone());
+                used += width;
+                continue;
+            }
+
+            let mut span = span.clone();
+            while !span.text.is_empty() && used + measure(&span) + ellipsis > max {
+                span.text.pop();
+            }
+
+            if used + measure(&span) + ellipsis <= max {
+                span.text.truncate(span.text.trim_end().len());
+                span.text.push_str(ELLIPSIS);
+                cut.push(span);
+            }
+            break;
+        }
+
+        if !cut.is_empty() {
+            kept.push(cut);
+        }
+        break;
+    }
+
+    kept
+}
+
+#[test]
+fn zones_are_placed_and_cut_short() {
+    let measure = |span: &Span| span.text.chars().count() as f32;
+    let zone = |modules: &[&str], priority| ZoneText {
+        modules: modules.iter().map(|m| vec![Span::plain(m)]).collect(),
+        separator: vec![Span::plain("|")],
+        spacing: 1.0,
+        priority,
+    };
+    let texts = |runs: Vec<(Span, f32, Option<Owner>)>| -> Vec<(String, f32)> {
+        runs.into_iter().map(|(span, pen, _)| (span.text, pen)).collect()
+    };
+
+    let zones = [zone(&["ab"], 1), zone(&["cd"], 0), zone(&["ef", "gh"], 2)];
+    assert_eq!(texts(layout(&zones, 20.0, 1.0, &measure)), vec![
+        ("ab".into(), 1.0), ("cd".into(), 9.0),
+        ("ef".into(), 12.0), ("|".into(), 15.0), ("gh".into(), 17.0),
+    ]);
+
+    let owners: Vec<_> = layout(&zones, 20.0, 1.0, &measure).into_iter().map(|(_, _, owner)| owner).collect();
+    assert_eq!(owners, vec![Some((0, 0)), Some((1, 0)), Some((2, 0)), None, Some((2, 1))]);
+
+    // the center zone goes first, then the left one loses its end
+    let zones = [zone(&["abcdef"], 1), zone(&["cd"], 0), zone(&["ef", "gh"], 2)];
+    assert_eq!(texts(layout(&zones, 13.0, 1.0, &measure)), vec![
+        ("ab…".into(), 1.0),
+        ("ef".into(), 5.0), ("|".into(), 8.0), ("gh".into(), 10.0),
+    ]);
+}
+
+/// Renders at scale 1 with the checked-in font and compares with `tests/golden/<name>.png`.
+/// `SVBAR_BLESS=1 cargo test` rewrites the images instead.
+#[cfg(test)]
+fn golden(name: &str, config: &str, outputs: &[(&str, &str)]) {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    // the output is given, any module type will do
+    let tables: String = outputs.iter().map(|(name, _)| format!("\n[module.{name}]\ntype = \"clock\"\n")).collect();
+    let source = format!("[font]\nfamily = \"{dir}/tests/fonts/DejaVuSansMono.ttf\"\nsize = 14\n{config}{tables}");
+    let (config, diagnostics) = ConfigState::from_source(&source);
+    assert_eq!(diagnostics, vec![]);
+
+    let outputs: Vec<(String, String)> = outputs.iter().map(|(name, output)| (name.to_string(), output.to_string())).collect();
+    let expected = format!("{dir}/tests/golden/{name}.png");
+    let actual = format!("{dir}/tests/golden/{name}.actual.png");
+
+    if std::env::var_os("SVBAR_BLESS").is_some() {
+        render_png(&config, &outputs, &expected).unwrap();
+        return;
+    }
+    render_png(&config, &outputs, &actual).unwrap();
+
+    let read = |path: &str| {
+        let decoder = png::Decoder::new(std::fs::File::open(path).unwrap_or_else(|why| panic!("{path}: {why}")));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        ((info.width, info.height), pixels)
+    };
+
+    let (size, pixels) = read(&actual);
+    let (expected_size, expected_pixels) = read(&expected);
+
+    // blending may round differently, anything more is a change in how the bar looks
+    let same = size == expected_size && pixels.iter().zip(&expected_pixels).all(|(a, b)| a.abs_diff(*b) <= 1);
+    assert!(same, "{name} doesn't look like {expected}, see {actual}");
+
+    std::fs::remove_file(&actual).unwrap();
+}
+
+#[test]
+fn span_fonts_are_loaded_ahead_of_drawing() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let font = format!("{dir}/tests/fonts/DejaVuSansMono.ttf");
+    let (config, _) = ConfigState::from_source(&format!("[font]\nfamily = \"{font}\"\n[module.clock]\n"));
+    let spans = markup::parse(&format!("<span font=\"{font}\">12</span>")).unwrap();
+
+    let mut fonts = Fonts::default();
+    fonts.load(&config.font);
+    let mut pixels = vec![0; 100 * 20 * 4];
+    let frame = Frame { config: &config, modules: vec![("clock", &spans)], scale: 1.0, revealed: 100, opacity: 1.0 };
+
+    // drawing falls back to the bar's font instead of looking it up
+    render(&mut Canvas { pixels: &mut pixels, width: 100, height: 20 }, &mut fonts, &frame);
+    assert!(fonts.spans.is_empty());
+
+    fonts.load_span_fonts(&config, [spans.as_slice()]);
+    assert!(fonts.spans[&font].is_some());
+}
+
+#[test]
+fn golden_zones_and_markup() {
+    golden("zones", "
+[bar]
+width = 400
+height = 24
+background = \"#202020\"
+foreground = \"#e0e0e0\"
+
+[zone.left]
+modules = [\"workspaces\"]
+
+[zone.center]
+modules = [\"title\"]
+
+[zone.right]
+modules = [\"audio\", \"clock\"]
+separator = \"<span alpha='0.5'>|</span>\"
+", &[
+        ("workspaces", "<span bg=\"#4060a0\"> 1 </span> 2 3"),
+        ("title", "<span underline=\"true\">editor</span>"),
+        ("audio", "<span fg=\"red\">42%</span>"),
+        ("clock", "12:34<span alpha=\"0.6\">:56</span>"),
+    ]);
+}
+
+#[test]
+fn golden_truncated() {
+    golden("truncated", "
+[bar]
+width = 200
+
+[zone.left]
+modules = [\"title\"]
+
+[zone.right]
+modules = [\"clock\"]
+", &[
+        ("title", "a window title far too long for the bar"),
+        ("clock", "12:34:56"),
+    ]);
+}
+
+#[test]
+fn golden_vertical() {
+    golden("vertical", "
+[bar]
+edge = \"left\"
+width = 200
+height = 80
+
+[zone.left]
+modules = [\"workspaces\"]
+
+[zone.right]
+modules = [\"audio\", \"clock\"]
+", &[
+        ("workspaces", "1 2 3"),
+        ("audio", "42%"),
+        ("clock", "12:34"),
+    ]);
+}
+
+#[test]
+fn golden_rotated() {
+    golden("rotated", "
+[bar]
+edge = \"right\"
+width = 200
+rotate_text = true
+
+[zone.left]
+modules = [\"title\"]
+
+[zone.right]
+modules = [\"clock\"]
+", &[
+        ("title", "editor"),
+        ("clock", "12:34"),
+    ]);
+}
+
+#[test]
+fn golden_translucent_gamma() {
+    // lines before the first section go to `[font]`
+    golden("translucent", "
+gamma_correct = true
+
+[bar]
+width = 200
+background = \"#10101080\"
+
+[zone.right]
+modules = [\"clock\"]
+", &[("clock", "12:34<span fg=\"#80c0ff\">:56</span>")]);
+}
@@ -0,0 +1,133 @@
+use smithay_client_toolkit::reexports::protocols::wp::{
+    fractional_scale::v1::client::{
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    },
+    viewporter::client::{
+        wp_viewport::WpViewport,
+        wp_viewporter::WpViewporter,
+    },
+};
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    globals::GlobalList,
+    protocol::wl_surface::WlSurface,
+};
+
+use super::BarWindow;
+
+/// Scales are in 120ths, the way `wp_fractional_scale_v1` reports them
+pub const SCALE_DENOMINATOR: u32 = 120;
+
+/// Globals needed for fractional scaling, only there if the compositor has
+/// both `wp_fractional_scale_manager_v1` and `wp_viewporter`
+pub struct ScaleGlobals {
+    manager: WpFractionalScaleManagerV1,
+    viewporter: WpViewporter,
+}
+
+impl ScaleGlobals {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<BarWindow>) -> Option<Self> {
+        Some(Self {
+            manager: globals.bind(qh, 1..=1, ()).ok()?,
+            viewporter: globals.bind(qh, 1..=1, ()).ok()?,
+        })
+    }
+
+    pub fn create(&self, surface: &WlSurface, qh: &QueueHandle<BarWindow>) -> FractionalScale {
+        FractionalScale {
+            viewport: self.viewporter.get_viewport(surface, qh, ()),
+            scale: self.manager.get_fractional_scale(surface, qh, surface.clone()),
+        }
+    }
+}
+
+/// Per-surface objects for fractional scaling
+pub struct FractionalScale {
+    pub viewport: WpViewport,
+    scale: WpFractionalScaleV1,
+}
+
+impl Drop for FractionalScale {
+    fn drop(&mut self) {
+        self.viewport.destroy();
+        self.scale.destroy();
+    }
+}
+
+/// Physical size of `logical` pixels at `scale`, rounded half away from zero like the protocol asks
+pub fn to_physical(logical: u32, scale: u32) -> u32 {
+    (logical * scale + SCALE_DENOMINATOR / 2) / SCALE_DENOMINATOR
+}
+
+impl BarWindow {
+    pub fn set_scale(&mut self, surface: &WlSurface, scale: u32) {
+        let Some(index) = self.bar_index(surface) else { return };
+        let bar = &mut self.bars[index];
+
+        if scale == bar.scale || scale == 0 {
+            return;
+        }
+
+        bar.scale = scale;
+        bar.invalidate();
+        self.request_bar_redraw(index);
+    }
+}
+
+/// The surface is the user data, to know which bar the scale is for
+impl Dispatch<WpFractionalScaleV1, WlSurface> for BarWindow {
+    fn event(
+        state: &mut Self,
+        _: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        surface: &WlSurface,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            state.set_scale(surface, scale);
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleManagerV1, ()> for BarWindow {
+    fn event(
+        _: &mut Self,
+        _: &WpFractionalScaleManagerV1,
+        _: <WpFractionalScaleManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+impl Dispatch<WpViewporter, ()> for BarWindow {
+    fn event(
+        _: &mut Self,
+        _: &WpViewporter,
+        _: <WpViewporter as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+impl Dispatch<WpViewport, ()> for BarWindow {
+    fn event(
+        _: &mut Self,
+        _: &WpViewport,
+        _: <WpViewport as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+#[test]
+fn physical_sizes() {
+    assert_eq!(to_physical(1920, 120), 1920);
+    assert_eq!(to_physical(24, 240), 48);
+    assert_eq!(to_physical(24, 180), 36);
+    assert_eq!(to_physical(25, 150), 31);
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use smithay_client_toolkit::reexports::calloop::{
+    LoopHandle, RegistrationToken,
+    timer::{TimeoutAction, Timer},
+};
+
+use crate::modules::Update;
+use super::BarWindow;
+
+/// Gives module `id` its own timer, event driven modules don't get one
+pub fn schedule_module(
+    handle: &LoopHandle<'static, BarWindow>,
+    id: u64,
+    update: Update,
+) -> Option<RegistrationToken> {
+    let first = next_refresh(update, SystemTime::now())?;
+
+    let token = handle
+        .insert_source(Timer::from_duration(first), move |_, _, window| {
+            if !window.refresh_module(id) {
+                return TimeoutAction::Drop;
+            }
+
+            match next_refresh(update, SystemTime::now()) {
+                Some(next) => TimeoutAction::ToDuration(next),
+                None => TimeoutAction::Drop,
+            }
+        })
+        .expect("Failed to insert a module timer");
+
+    Some(token)
+}
+
+fn next_refresh(update: Update, now: SystemTime) -> Option<Duration> {
+    match update {
+        Update::Every(interval) => Some(interval),
+        Update::Aligned(interval) => {
+            let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+            let interval = interval.as_nanos().max(1);
+
+            Some(Duration::from_nanos((interval - now % interval) as u64))
+        }
+        Update::EventDriven => None,
+    }
+}
+
+#[test]
+fn aligned_refresh_waits_for_the_boundary() {
+    let now = UNIX_EPOCH + Duration::from_millis(12_250);
+    let second = Duration::from_secs(1);
+
+    assert_eq!(next_refresh(Update::Aligned(second), now), Some(Duration::from_millis(750)));
+    assert_eq!(next_refresh(Update::Aligned(Duration::from_secs(60)), now), Some(Duration::from_millis(47_750)));
+    assert_eq!(next_refresh(Update::Every(second), now), Some(second));
+    assert_eq!(next_refresh(Update::EventDriven, now), None);
+}
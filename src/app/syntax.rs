@@ -0,0 +1,381 @@
+use super::diagnostic::Diagnostic;
+
+/// Minimal ini/toml-like syntax used by the config file:
+///
+/// ```text
+/// # comment
+/// [section]
+/// key = "string"
+/// key = 12
+/// key = 1.5
+/// key = true
+/// key = [ "a", "b" ]
+/// key = bare words are strings too
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_)  => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_)   => "float",
+            Value::Bool(_)    => "boolean",
+            Value::Array(_)   => "array",
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None
+        }
+    }
+
+    /// Integers are accepted wherever a float is expected
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f)   => Some(*f),
+            Value::Integer(i) => Some(*i as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    /// Source text of the value, for keys that want to interpret it themselves
+    pub raw: String,
+    pub line: usize,
+    pub column: usize,
+    pub value_column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Empty for keys written before the first `[section]` header
+    pub name: String,
+    pub line: usize,
+    pub entries: Vec<Entry>,
+}
+
+impl Section {
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().rev().find(|e| e.key == key)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Document {
+    pub sections: Vec<Section>,
+}
+
+impl Document {
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+}
+
+pub fn parse(source: &str) -> (Document, Vec<Diagnostic>) {
+    let mut document = Document::default();
+    let mut errors = Vec::new();
+
+    let mut current = Section { name: String::new(), line: 0, entries: Vec::new() };
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut cursor = Cursor::new(raw);
+        cursor.skip_whitespace();
+
+        match cursor.peek() {
+            None | Some('#') => continue,
+            Some('[') => {
+                cursor.bump();
+                let start = cursor.column();
+                let name = cursor.take_while(|c| c != ']').trim().to_string();
+
+                if cursor.peek() != Some(']') {
+                    errors.push(Diagnostic::error(line, cursor.column(), "expected `]`"));
+                    continue;
+                }
+                cursor.bump();
+
+                if name.is_empty() || !name.chars().all(is_name_char) {
+                    errors.push(Diagnostic::error(line, start, format!("invalid section name `{name}`")));
+                    continue;
+                }
+                if let Err(message) = cursor.expect_end() {
+                    errors.push(Diagnostic::error(line, cursor.column(), message));
+                    continue;
+                }
+
+                let previous = std::mem::replace(
+                    &mut current,
+                    Section { name, line, entries: Vec::new() }
+                );
+                push_section(&mut document, previous);
+            }
+            Some(_) => {
+                let column = cursor.column();
+                let key = cursor.take_while(|c| c != '=').trim().to_string();
+
+                if cursor.peek() != Some('=') {
+                    errors.push(Diagnostic::error(line, column, "expected `key = value`"));
+                    continue;
+                }
+                cursor.bump();
+
+                if key.is_empty() || !key.chars().all(is_name_char) {
+                    errors.push(Diagnostic::error(line, column, format!("invalid key `{key}`")));
+                    continue;
+                }
+
+                cursor.skip_whitespace();
+                let start = cursor.offset;
+                let value_column = cursor.column();
+                let value = match cursor.parse_value(false) {
+                    Ok(value) => value,
+                    Err(message) => {
+                        errors.push(Diagnostic::error(line, cursor.column(), message));
+                        continue;
+                    }
+                };
+                let raw = raw[start..cursor.offset].trim().to_string();
+
+                if let Err(message) = cursor.expect_end() {
+                    errors.push(Diagnostic::error(line, cursor.column(), message));
+                    continue;
+                }
+
+                current.entries.push(Entry { key, value, raw, line, column, value_column });
+            }
+        }
+    }
+
+    push_section(&mut document, current);
+    (document, errors)
+}
+
+/// Repeated headers are merged into the first one so later keys override earlier ones
+fn push_section(document: &mut Document, section: Section) {
+    if section.name.is_empty() && section.entries.is_empty() {
+        return;
+    }
+
+    match document.sections.iter_mut().find(|s| s.name == section.name) {
+        Some(existing) => existing.entries.extend(section.entries),
+        None => document.sections.push(section),
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+struct Cursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    column: usize,
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { chars: line.chars().peekable(), column: 1, offset: 0 }
+    }
+
+    fn column(&self) -> usize {
+        self.column
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if let Some(c) = c {
+            self.column += 1;
+            self.offset += c.len_utf8();
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek() && predicate(c) {
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+
+    /// Only whitespace or a comment may follow a value
+    fn expect_end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            None | Some('#') => Ok(()),
+            Some(c) => Err(format!("unexpected `{c}` after value")),
+        }
+    }
+
+    fn parse_value(&mut self, in_array: bool) -> Result<Value, String> {
+        match self.peek() {
+            None => Err("missing value".into()),
+            Some('"') => self.parse_string().map(Value::String),
+            Some('[') if !in_array => {
+                self.bump();
+                let mut items = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(']') {
+                        self.bump();
+                        break;
+                    }
+
+                    items.push(self.parse_value(true)?);
+                    self.skip_whitespace();
+
+                    match self.bump() {
+                        Some(',') => continue,
+                        Some(']') => break,
+                        Some(c) => return Err(format!("expected `,` or `]`, found `{c}`")),
+                        None => return Err("unterminated array".into()),
+                    }
+                }
+
+                Ok(Value::Array(items))
+            }
+            Some(_) => {
+                let mut word = String::new();
+                let mut last_was_space = false;
+
+                while let Some(c) = self.peek() {
+                    if c == '#' && last_was_space { break; }
+                    if in_array && matches!(c, ',' | ']') { break; }
+
+                    last_was_space = c.is_whitespace();
+                    word.push(c);
+                    self.bump();
+                }
+
+                Ok(bare_value(word.trim()))
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.bump();
+        let mut out = String::new();
+
+        loop {
+            match self.bump() {
+                None => return Err("unterminated string".into()),
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some('n')  => out.push('\n'),
+                    Some('t')  => out.push('\t'),
+                    Some('"')  => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some(c) => return Err(format!("unknown escape `\\{c}`")),
+                    None => return Err("unterminated string".into()),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+}
+
+fn bare_value(word: &str) -> Value {
+    match word {
+        "true"  => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+
+    if let Ok(int) = word.parse::<i64>() {
+        return Value::Integer(int);
+    }
+    if word.contains('.') && let Ok(float) = word.parse::<f64>() {
+        return Value::Float(float);
+    }
+
+    Value::String(word.to_string())
+}
+
+#[test]
+fn parse_sections_and_values() {
+    let (document, errors) = parse(concat!(
+        "background=000000\n",
+        "# comment\n",
+        "[bar]\n",
+        "height = 24 # trailing comment\n",
+        "name = \"quoted # not a comment\"\n",
+        "scale = 1.5\n",
+        "[modules]\n",
+        "order = [ \"audio\", clock ]\n",
+        "[bar]\n",
+        "height = 30\n",
+    ));
+
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let legacy = document.section("").unwrap();
+    assert_eq!(legacy.get("background").unwrap().value, Value::Integer(0));
+    assert_eq!(legacy.get("background").unwrap().raw, "000000");
+
+    let bar = document.section("bar").unwrap();
+    assert_eq!(bar.get("height").unwrap().value, Value::Integer(30));
+    assert_eq!(bar.get("name").unwrap().value.as_str(), Some("quoted # not a comment"));
+    assert_eq!(bar.get("scale").unwrap().value.as_float(), Some(1.5));
+
+    let order = document.section("modules").unwrap().get("order").unwrap();
+    assert_eq!(order.value, Value::Array(vec![
+        Value::String("audio".into()),
+        Value::String("clock".into()),
+    ]));
+}
+
+#[test]
+fn parse_reports_positions() {
+    let (_, errors) = parse("[bar\nkey\nother = \"open\n");
+
+    assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(errors[1].column, 1);
+}
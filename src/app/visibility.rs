@@ -0,0 +1,324 @@
+use std::time::{Duration, Instant};
+
+use super::config::{RevealConfig, RevealMode};
+
+/// How much of a bar that isn't exclusive is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    Hidden,
+    /// On its way out, shown `length` logical pixels along the edge.
+    /// In hover mode it becomes `Revealed` once the pointer rests for the reveal delay.
+    Peeking { length: u32 },
+    /// Shown in full until the pointer leaves
+    Revealed,
+    /// Shown in full until it's unpinned
+    Pinned,
+}
+
+/// What the pointer did, in logical pixels along the edge and away from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pointer {
+    Motion { along: f64, across: f64 },
+    Leave,
+    TogglePin,
+}
+
+pub struct Reveal {
+    pub visibility: Visibility,
+    /// When `visibility` last changed, the reveal delay and minimum visible time count from here
+    since: Instant,
+    /// The pointer is on the bar
+    hovered: bool,
+    /// Kept shown until then even without the pointer, after it left or a module changed
+    hold_until: Option<Instant>,
+}
+
+impl Reveal {
+    pub fn new(now: Instant) -> Self {
+        Self { visibility: Visibility::Hidden, since: now, hovered: false, hold_until: None }
+    }
+
+    /// Returns whether the visibility changed
+    pub fn handle(&mut self, config: &RevealConfig, pointer: Pointer, now: Instant) -> bool {
+        use Visibility::*;
+        let (mode, delay) = (config.mode, config.delay);
+
+        if mode == RevealMode::Always {
+            return false;
+        }
+
+        // a click is on the bar too
+        self.hovered = pointer != Pointer::Leave;
+
+        let next = match (mode, self.visibility, pointer) {
+            (_, Pinned, Pointer::TogglePin) => Revealed,
+            (_, _, Pointer::TogglePin) => Pinned,
+            (_, Pinned, _) => Pinned,
+
+            (_, _, Pointer::Leave) => {
+                if self.shows_something() {
+                    self.hold(now + config.hide_delay);
+                }
+                return self.tick(config, now);
+            }
+
+            (RevealMode::Hover, Hidden, Pointer::Motion { .. }) if delay.is_zero() => Revealed,
+            (RevealMode::Hover, Hidden, Pointer::Motion { .. }) => Peeking { length: 0 },
+
+            // pulled out along the edge, starting in the corner
+            (RevealMode::EdgeDrag, Hidden, Pointer::Motion { along, across }) if along <= 0.0 && across <= 0.0 => {
+                Peeking { length: 0 }
+            }
+            (RevealMode::EdgeDrag, Peeking { .. }, Pointer::Motion { along, across }) if across <= 0.0 => {
+                Peeking { length: along.max(0.0) as u32 }
+            }
+            // moving away from the edge lets go of it, going down the side from the corner doesn't
+            (RevealMode::EdgeDrag, Peeking { .. }, Pointer::Motion { along, .. }) if along > 0.0 => Hidden,
+
+            (_, visibility, Pointer::Motion { .. }) => visibility,
+        };
+
+        self.set(next, now)
+    }
+
+    /// Shows the whole bar for at least `duration`, like an OSD.
+    /// Returns whether the visibility changed, the deadline may have either way.
+    pub fn show_for(&mut self, config: &RevealConfig, duration: Duration, now: Instant) -> bool {
+        if config.mode == RevealMode::Always || self.visibility == Visibility::Pinned {
+            return false;
+        }
+
+        self.hold(now + duration);
+        self.set(Visibility::Revealed, now)
+    }
+
+    /// Shows the whole bar until it's hidden or unpinned, returns whether the visibility changed
+    pub fn pin(&mut self, config: &RevealConfig, now: Instant) -> bool {
+        config.mode != RevealMode::Always && self.set(Visibility::Pinned, now)
+    }
+
+    /// Hides the bar right away, even pinned or held, returns whether the visibility changed
+    pub fn hide(&mut self, config: &RevealConfig, now: Instant) -> bool {
+        if config.mode == RevealMode::Always {
+            return false;
+        }
+
+        self.hold_until = None;
+        self.set(Visibility::Hidden, now)
+    }
+
+    /// When `tick` should be called next, if ever
+    pub fn deadline(&self, config: &RevealConfig) -> Option<Instant> {
+        match (config.mode, self.visibility) {
+            (RevealMode::Always, _) | (_, Visibility::Hidden | Visibility::Pinned) => None,
+
+            // hidden once the pointer is gone and nothing holds it anymore
+            (_, visibility) if !self.hovered => {
+                let held = self.hold_until.unwrap_or(self.since);
+                Some(match visibility {
+                    Visibility::Revealed => held.max(self.since + config.min_visible),
+                    _ => held,
+                })
+            }
+
+            (RevealMode::Hover, Visibility::Peeking { .. }) => Some(self.since + config.delay),
+            // a bar pulled out along the edge only follows the pointer, it's never shown in full by itself
+            _ => None,
+        }
+    }
+
+    /// Applies timeouts that ran out by `now`, returns whether the visibility changed
+    pub fn tick(&mut self, config: &RevealConfig, now: Instant) -> bool {
+        match self.deadline(config) {
+            Some(deadline) if deadline > now => false,
+            Some(_) if self.hovered => self.set(Visibility::Revealed, now),
+            Some(_) => {
+                self.hold_until = None;
+                self.set(Visibility::Hidden, now)
+            }
+            None => false,
+        }
+    }
+
+    /// How much of a bar `length` long is shown, in the same unit
+    pub fn shown(&self, mode: RevealMode, length: f32) -> f32 {
+        match (mode, self.visibility) {
+            (RevealMode::Always, _) => length,
+            (_, Visibility::Hidden) => 0.0,
+            (_, Visibility::Peeking { length: peeking }) => (peeking as f32).min(length),
+            (_, Visibility::Revealed | Visibility::Pinned) => length,
+        }
+    }
+
+    fn shows_something(&self) -> bool {
+        matches!(self.visibility, Visibility::Revealed | Visibility::Peeking { length: 1.. })
+    }
+
+    fn hold(&mut self, until: Instant) {
+        self.hold_until = Some(self.hold_until.map_or(until, |held| held.max(until)));
+    }
+
+    fn set(&mut self, visibility: Visibility, now: Instant) -> bool {
+        if visibility == self.visibility {
+            return false;
+        }
+
+        self.visibility = visibility;
+        self.since = now;
+        true
+    }
+}
+
+#[cfg(test)]
+fn with_mode(mode: RevealMode) -> RevealConfig {
+    use super::config::ConfigState;
+
+    RevealConfig { mode, delay: Duration::from_millis(300), ..ConfigState::default().reveal }
+}
+
+/// Feeds `pointer` 10ms apart, returns the state and the time after the last event
+#[cfg(test)]
+fn run(config: &RevealConfig, pointer: &[Pointer]) -> (Reveal, Instant) {
+    let start = Instant::now();
+    let mut reveal = Reveal::new(start);
+
+    for (i, event) in pointer.iter().enumerate() {
+        reveal.handle(config, *event, start + Duration::from_millis(10 * i as u64));
+    }
+
+    (reveal, start + Duration::from_millis(10 * pointer.len() as u64))
+}
+
+#[test]
+fn edge_drag_follows_the_pointer() {
+    use Pointer::*;
+    let config = with_mode(RevealMode::EdgeDrag);
+
+    let (mut reveal, now) = run(&config, &[
+        Motion { along: 0.0, across: 0.0 },
+        Motion { along: 120.0, across: 0.0 },
+    ]);
+    assert_eq!(reveal.visibility, Visibility::Peeking { length: 120 });
+    assert_eq!(reveal.shown(config.mode, 1920.0), 120.0);
+
+    // resting there keeps it where it is
+    assert_eq!(reveal.deadline(&config), None);
+    assert!(!reveal.tick(&config, now + config.delay));
+    assert_eq!(reveal.visibility, Visibility::Peeking { length: 120 });
+
+    let (reveal, _) = run(&config, &[
+        Motion { along: 0.0, across: 0.0 },
+        Motion { along: 120.0, across: 0.0 },
+        Motion { along: 130.0, across: 5.0 },
+    ]);
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+
+    // only a drag starting in the corner counts
+    let (reveal, _) = run(&config, &[Motion { along: 300.0, across: 0.0 }]);
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+    assert_eq!(reveal.deadline(&config), None);
+}
+
+#[test]
+fn hover_reveals_after_the_delay() {
+    use Pointer::*;
+    let config = with_mode(RevealMode::Hover);
+
+    let (mut reveal, now) = run(&config, &[Motion { along: 500.0, across: 10.0 }]);
+    assert_eq!(reveal.visibility, Visibility::Peeking { length: 0 });
+    assert!(reveal.tick(&config, now + config.delay));
+    assert_eq!(reveal.visibility, Visibility::Revealed);
+
+    reveal.handle(&config, Leave, now + config.delay);
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+
+    // passing over it doesn't
+    let (mut reveal, now) = run(&config, &[Motion { along: 500.0, across: 10.0 }, Leave]);
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+    assert!(!reveal.tick(&config, now + config.delay));
+}
+
+#[test]
+fn pinned_stays_until_unpinned() {
+    use Pointer::*;
+    let config = with_mode(RevealMode::EdgeDrag);
+
+    let (mut reveal, now) = run(&config, &[TogglePin, Leave, Motion { along: 50.0, across: 3.0 }]);
+    assert_eq!(reveal.visibility, Visibility::Pinned);
+
+    reveal.handle(&config, TogglePin, now);
+    assert_eq!(reveal.visibility, Visibility::Revealed);
+    reveal.handle(&config, Leave, now);
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+
+    let (reveal, _) = run(&with_mode(RevealMode::Always), &[TogglePin, Leave]);
+    assert_eq!(reveal.shown(RevealMode::Always, 1920.0), 1920.0);
+}
+
+#[test]
+fn hiding_waits_for_the_delays() {
+    use Pointer::*;
+    let config = RevealConfig {
+        hide_delay: Duration::from_millis(500),
+        min_visible: Duration::from_secs(2),
+        ..with_mode(RevealMode::Hover)
+    };
+    let start = Instant::now();
+    let mut reveal = Reveal::new(start);
+    let at = |ms| start + Duration::from_millis(ms);
+
+    reveal.handle(&config, Motion { along: 10.0, across: 10.0 }, at(0));
+    reveal.tick(&config, at(300));
+    assert_eq!(reveal.visibility, Visibility::Revealed);
+
+    // coming back within the hide delay keeps it
+    reveal.handle(&config, Leave, at(3000));
+    assert_eq!(reveal.deadline(&config), Some(at(3500)));
+    reveal.handle(&config, Motion { along: 10.0, across: 10.0 }, at(3200));
+    assert_eq!(reveal.deadline(&config), None);
+
+    reveal.handle(&config, Leave, at(4000));
+    assert!(!reveal.tick(&config, at(4499)));
+    assert!(reveal.tick(&config, at(4500)));
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+
+    // shown for a module, at least for the minimum time
+    assert!(reveal.show_for(&config, Duration::from_secs(1), at(5000)));
+    assert_eq!(reveal.deadline(&config), Some(at(7000)));
+    assert!(reveal.tick(&config, at(7000)));
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+}
+
+#[test]
+fn hiding_overrides_pins_and_holds() {
+    let config = with_mode(RevealMode::Hover);
+    let now = Instant::now();
+    let mut reveal = Reveal::new(now);
+
+    assert!(reveal.pin(&config, now));
+    assert!(reveal.hide(&config, now));
+    assert_eq!(reveal.visibility, Visibility::Hidden);
+
+    reveal.show_for(&config, Duration::from_secs(5), now);
+    assert!(reveal.hide(&config, now));
+    assert_eq!(reveal.deadline(&config), None);
+
+    let always = with_mode(RevealMode::Always);
+    assert!(!reveal.pin(&always, now));
+    assert!(!reveal.hide(&always, now));
+}
+
+#[test]
+fn default_config_never_reveals_by_itself() {
+    use super::config::ConfigState;
+    use Pointer::*;
+    let config = ConfigState::default().reveal;
+
+    let (mut reveal, now) = run(&config, &[
+        Motion { along: 0.0, across: 0.0 },
+        Motion { along: 400.0, across: 0.0 },
+    ]);
+    assert_eq!(reveal.deadline(&config), None);
+    assert!(!reveal.tick(&config, now + Duration::from_secs(60)));
+    assert_eq!(reveal.visibility, Visibility::Peeking { length: 400 });
+}
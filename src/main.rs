@@ -1,34 +1,44 @@
 mod modules;
 
 mod app;
-use app::BarWindow;
+use app::{BarWindow, ConfigState, FontState, reap_children, render_png, serve_control, watch_config};
 
-use std::time::Duration;
 use std::sync::{Arc, RwLock};
 
 use smithay_client_toolkit::{
-    compositor::CompositorState,
-
     reexports::calloop::EventLoop,
     reexports::calloop_wayland_source::WaylandSource,
-
-    shell::{
-        wlr_layer::{
-            Layer, LayerShell,
-            Anchor, KeyboardInteractivity
-        },
-        WaylandSurface,
-    },
 };
 use wayland_client::{
     globals::registry_queue_init,
     Connection
 };
 
-const WINDOW_WIDTH:  u32 = 1920;
-const WINDOW_HEIGHT: u32 = 24;
-
 fn main() {
+    let mut args = std::env::args().skip(1);
+
+    match args.next().as_deref() {
+        None => {}
+        Some("--check-config") => {
+            let path = args.next().unwrap_or_else(ConfigState::default_path);
+            std::process::exit(check_config(&path));
+        }
+        Some("--render-png") => {
+            let Some(path) = args.next() else {
+                eprintln!("usage: svbar --render-png <out.png>");
+                std::process::exit(2);
+            };
+            std::process::exit(render(&path));
+        }
+        Some(arg) => {
+            eprintln!("unknown argument `{arg}`");
+            eprintln!("usage: svbar [--check-config [path] | --render-png <out.png>]");
+            std::process::exit(2);
+        }
+    }
+
+    let config = ConfigState::new();
+
     let conn = Connection::connect_to_env().unwrap();
 
     let (globals, event_queue) = registry_queue_init(&conn).unwrap();
@@ -38,36 +48,81 @@ fn main() {
     let loop_handle = event_loop.handle();
     WaylandSource::new(conn.clone(), event_queue).insert(loop_handle).unwrap();
 
-    let compositor = CompositorState::bind(&globals, &qh).expect("wl_compositor not available");
-    let layer_shell = LayerShell::bind(&globals, &qh).expect("zwlr_layer_shell_v1 not available");
-
-    let surface = compositor.create_surface(&qh);
-    let surface = layer_shell.create_layer_surface(&qh, surface, Layer::Top, None::<String>, None);
-    surface.set_anchor(Anchor::TOP);
-    surface.set_keyboard_interactivity(KeyboardInteractivity::None);
-    surface.set_size(WINDOW_WIDTH, WINDOW_HEIGHT);
-    surface.commit();
-
     let exiting = Arc::new(RwLock::new(false));
 
+    watch_config(&event_loop.handle(), &ConfigState::default_path());
+    reap_children(&event_loop.handle());
+    let socket = serve_control(&event_loop.handle());
+
+    // bars are created as the outputs are announced
     let mut window = BarWindow::new(
-        WINDOW_WIDTH, WINDOW_HEIGHT, 
-        &globals, &qh, 
-        surface, exiting.clone()
+        config,
+        &globals, &qh, event_loop.handle(),
+        exiting.clone()
     );
 
+    // the loop sleeps until something happens, so ctrl-c has to wake it up
+    let signal = event_loop.get_signal();
     ctrlc::set_handler(move || {
         let mut exiting = exiting.write().expect("Failed to handle ctrlc, not able to write");
         *exiting = true;
+        signal.wakeup();
     }).expect("failed to set handler");
 
     loop {
-        event_loop.dispatch(Duration::from_millis(15), &mut window).unwrap();
+        event_loop.dispatch(None, &mut window).unwrap();
 
         if let Ok(exiting) = window.state.exiting.clone().read() && *exiting {
             window.state.module_cleanup();
+            if let Some(socket) = &socket {
+                let _ = std::fs::remove_file(socket);
+            }
             println!("exiting");
             break;
         }
     }
 }
+
+/// Prints every problem in the config file, exit code is non-zero if any of them is an error
+fn check_config(path: &str) -> i32 {
+    let (config, report) = ConfigState::load(path);
+    eprint!("{report}");
+
+    let font = FontState::load(&config.font.families);
+    match &font {
+        Ok(font) => font.missing.iter().for_each(|why| eprintln!("warning: {why}")),
+        Err(why) => eprintln!("error: {why}"),
+    }
+
+    if report.has_errors() || font.is_err() {
+        eprintln!("{path}: config has errors");
+        1
+    }
+    else {
+        println!("{path}: ok");
+        0
+    }
+}
+
+/// Draws the bar with the modules' current output into a PNG, without a compositor
+fn render(path: &str) -> i32 {
+    let config = ConfigState::new();
+
+    // modules that can't start here, like audio without a sound server, are left out
+    let outputs: Vec<(String, String)> = config.modules.iter()
+        .filter_map(|module_config| {
+            let mut module = modules::from_config(module_config)?;
+            let output = module.display();
+            module.clean_up();
+            Some((module_config.name.clone(), output))
+        })
+        .collect();
+
+    match render_png(&config, &outputs, path) {
+        Ok(()) => 0,
+        Err(why) => {
+            eprintln!("{why}");
+            1
+        }
+    }
+}
@@ -1,34 +1,71 @@
-use super::ModuleInfo;
+use std::time::Duration;
+
+use super::{ModuleInfo, Trigger, Update};
+use crate::app::ModuleConfig;
 use volume::VolumeContext;
 
+const MUTED_COLOR: &str = "red";
+/// Percent a scroll step changes the volume by
+const STEP: i32 = 5;
+/// Percent scrolling doesn't raise the volume past
+const MAX_VOLUME: u32 = 100;
+
 pub struct AudioModule {
     context: VolumeContext,
-    previous: u8,
+    muted_color: String,
+    step: i32,
+    max_volume: u32,
+    previous: String,
 }
 impl AudioModule {
-    pub fn new() -> Self { 
-        Self { 
-            context: VolumeContext::new().expect("Failed to get volume context"),
-            previous: 0 
-        } 
+    /// `muted_color` is what the volume is shown in while muted,
+    /// scrolling changes it by `step` percent up to `max_volume` percent
+    pub fn new(config: &ModuleConfig) -> Result<Self, String> { 
+        Ok(Self { 
+            context: VolumeContext::new()?,
+            muted_color: config.get_str("muted_color").unwrap_or(MUTED_COLOR).to_string(),
+            step: config.get_int("step").map_or(STEP, |step| step.clamp(1, 100) as i32),
+            max_volume: config.get_int("max_volume").map_or(MAX_VOLUME, |max| max.clamp(1, 150) as u32),
+            previous: "0".into(),
+        })
     } 
 }
 
 impl ModuleInfo for AudioModule {
     fn display(&mut self) -> String {
-        match self.context.get() {
-            Ok(new) => {
-                self.previous = new;
-                new.to_string()
+        match self.context.status() {
+            Ok(volume) => {
+                self.previous = match volume.muted {
+                    true => format!("<span fg=\"{}\">{}</span>", self.muted_color, volume.level),
+                    false => volume.level.to_string(),
+                };
+                self.previous.clone()
             }
             Err(why) => {
                 println!("Failed to get volume: {why}");
-                self.previous.to_string()
+                self.previous.clone()
             }
         }
     }
+    fn update(&self) -> Update {
+        Update::Every(Duration::from_millis(250))
+    }
 
     fn clean_up(&mut self) {
-        let _ = self.context.exit();
+        self.context.exit();
+    }
+
+    fn handle(&mut self, trigger: Trigger) -> bool {
+        let result = match trigger {
+            Trigger::ScrollUp => self.context.change_volume(self.step, self.max_volume),
+            Trigger::ScrollDown => self.context.change_volume(-self.step, self.max_volume),
+            Trigger::LeftClick => self.context.toggle_mute().map(|_| ()),
+            _ => return false,
+        };
+
+        if let Err(why) = result {
+            eprintln!("Failed to change the volume: {why}");
+        }
+        true
     }
 }
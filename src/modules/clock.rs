@@ -1,17 +1,110 @@
-use chrono::{Utc, FixedOffset};
-use super::module::ModuleInfo;
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc, FixedOffset};
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use super::module::{ModuleInfo, Update};
+use crate::app::ModuleConfig;
 
 const TIMEZONE_OFFSET: FixedOffset = FixedOffset::east_opt(3 * 60 * 60).expect("Not a valid offset");
+const FORMAT: &str = "%d %H %M %S";
+/// Seconds are drawn fainter than the rest, they change too often to be worth the attention
+const SECONDS_ALPHA: f64 = 0.6;
 
-pub struct ClockModule;
+pub struct ClockModule {
+    offset: FixedOffset,
+    format: String,
+    seconds_alpha: f64,
+}
 
 impl ClockModule {
-    pub fn new() -> Self { Self {} }
+    /// `utc_offset` is in hours, `format` is a chrono strftime string that can contain markup,
+    /// `seconds_alpha` dims `%S`, 1 to draw it like the rest
+    pub fn new(config: &ModuleConfig) -> Self {
+        let offset = config.get_float("utc_offset")
+            .and_then(|hours| FixedOffset::east_opt((hours * 60.0 * 60.0) as i32))
+            .unwrap_or(TIMEZONE_OFFSET);
+
+        let format = config.get_str("format")
+            .filter(|format| check_format(format).is_ok())
+            .unwrap_or(FORMAT)
+            .to_string();
+        let seconds_alpha = config.get_float("seconds_alpha")
+            .filter(|alpha| (0.0..=1.0).contains(alpha))
+            .unwrap_or(SECONDS_ALPHA);
+
+        Self { offset, format, seconds_alpha }
+    }
+}
+
+/// Whether chrono understands every specifier in `format`
+pub fn check_format(format: &str) -> Result<(), String> {
+    match StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        true => Err(format!("`{format}` isn't a valid strftime format")),
+        false => Ok(()),
+    }
+}
+
+impl ClockModule {
+    /// `time` in the configured format, with the seconds wrapped in markup that dims them
+    fn format(&self, time: DateTime<FixedOffset>) -> String {
+        let mut output = String::new();
+        let mut rest = Vec::new();
+
+        for item in StrftimeItems::new(&self.format) {
+            if self.seconds_alpha < 1.0 && matches!(item, Item::Numeric(Numeric::Second, _)) {
+                let _ = write!(output, "{}", time.format_with_items(std::mem::take(&mut rest).into_iter()));
+                let _ = write!(output, "<span alpha=\"{}\">{}</span>", self.seconds_alpha, time.format_with_items(std::iter::once(item)));
+            }
+            else {
+                rest.push(item);
+            }
+        }
+
+        let _ = write!(output, "{}", time.format_with_items(rest.into_iter()));
+        output
+    }
 }
 
 impl ModuleInfo for ClockModule {
     fn display(&mut self) -> String {
-        let now = Utc::now().with_timezone(&TIMEZONE_OFFSET);
-        now.format("%d %H %M %S").to_string()
+        self.format(Utc::now().with_timezone(&self.offset))
+    }
+
+    fn update(&self) -> Update {
+        // `%T`, `%c` and the like come out of the parser as their parts, seconds among them
+        let seconds = StrftimeItems::new(&self.format).any(|item| matches!(item,
+            Item::Numeric(Numeric::Second | Numeric::Timestamp, _) | Item::Fixed(Fixed::RFC3339 | Fixed::RFC2822)
+        ));
+
+        match seconds {
+            true => Update::Aligned(Duration::from_secs(1)),
+            false => Update::Aligned(Duration::from_secs(60)),
+        }
+    }
+}
+
+#[test]
+fn seconds_are_dimmed() {
+    let clock = |format: &str| ClockModule { offset: TIMEZONE_OFFSET, format: format.into(), seconds_alpha: 0.5 };
+    let time = DateTime::parse_from_rfc3339("2026-10-18T09:05:07+03:00").unwrap();
+
+    assert_eq!(clock("%H:%M:%S").format(time), "09:05:<span alpha=\"0.5\">07</span>");
+    assert_eq!(clock("%T").format(time), "09:05:<span alpha=\"0.5\">07</span>");
+    // an escaped %%S is a literal, not seconds
+    assert_eq!(clock("%H:%M %%S").format(time), "09:05 %S");
+}
+
+#[test]
+fn refreshed_as_often_as_shown() {
+    let clock = |format: &str| ClockModule { offset: TIMEZONE_OFFSET, format: format.into(), seconds_alpha: 1.0 };
+    let second = Update::Aligned(Duration::from_secs(1));
+    let minute = Update::Aligned(Duration::from_secs(60));
+
+    for format in ["%S", "%-S", "%_S", "%0S", "%T", "%c", "%s", "%+"] {
+        assert_eq!(clock(format).update(), second, "{format}");
+    }
+    for format in ["%H:%M", "%%S", "%d %b"] {
+        assert_eq!(clock(format).update(), minute, "{format}");
     }
 }
@@ -1,8 +1,26 @@
 mod module;
-pub use module::ModuleInfo;
+pub use module::{ModuleInfo, OPTIONS, OptionType, TRIGGERS, Trigger, Update};
 
 mod clock;
-pub use clock::ClockModule;
+pub use clock::{ClockModule, check_format};
 
 mod audio;
 pub use audio::AudioModule;
+
+use crate::app::ModuleConfig;
+
+/// Values accepted by `type` in a `[module.<name>]` table
+pub const KINDS: &[&str] = &["clock", "audio"];
+
+/// None if the module couldn't be started, like audio without a sound server, which is logged
+pub fn from_config(config: &ModuleConfig) -> Option<Box<dyn ModuleInfo>> {
+    let module: Result<Box<dyn ModuleInfo>, String> = match config.kind.as_str() {
+        "clock" => Ok(Box::new(ClockModule::new(config))),
+        "audio" => AudioModule::new(config).map(|module| Box::new(module) as _),
+        kind => Err(format!("unknown module type `{kind}`")),
+    };
+
+    module
+        .inspect_err(|why| eprintln!("Failed to start module `{}`: {why}", config.name))
+        .ok()
+}
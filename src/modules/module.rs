@@ -1,4 +1,63 @@
+use std::time::Duration;
+
+/// When a module's output is refreshed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    /// Every interval, counted from the last refresh
+    Every(Duration),
+    /// On wall clock multiples of the interval, like every full second
+    Aligned(Duration),
+    /// Only when something asks for it, there's no timer
+    EventDriven,
+}
+
+/// Pointer input over a module, bound with `on_click` and the like in its table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    LeftClick,
+    MiddleClick,
+    RightClick,
+    ScrollUp,
+    ScrollDown,
+}
+
+/// Keys of a module table and what they bind
+pub const TRIGGERS: [(&str, Trigger); 5] = [
+    ("on_click", Trigger::LeftClick),
+    ("on_middle_click", Trigger::MiddleClick),
+    ("on_right_click", Trigger::RightClick),
+    ("on_scroll_up", Trigger::ScrollUp),
+    ("on_scroll_down", Trigger::ScrollDown),
+];
+
+/// What a module option holds, the config loader checks it like the bar's own keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionType {
+    /// A number from `min` to `max`
+    Number { min: f64, max: f64 },
+    /// A whole number from `min` to `max`
+    Integer { min: i64, max: i64 },
+    Color,
+    /// A chrono strftime string
+    Format,
+}
+
+/// The options each type of module reads from its table, `(type, key, what it holds)`
+pub const OPTIONS: &[(&str, &str, OptionType)] = &[
+    ("clock", "format", OptionType::Format),
+    ("clock", "utc_offset", OptionType::Number { min: -12.0, max: 14.0 }),
+    ("clock", "seconds_alpha", OptionType::Number { min: 0.0, max: 1.0 }),
+    ("audio", "muted_color", OptionType::Color),
+    ("audio", "step", OptionType::Integer { min: 1, max: 100 }),
+    ("audio", "max_volume", OptionType::Integer { min: 1, max: 150 }),
+];
+
 pub trait ModuleInfo {
+    /// Text to show, `<span>` markup styles parts of it (see `app::markup`)
     fn display(&mut self) -> String;
+    fn update(&self) -> Update { Update::Every(Duration::from_secs(1)) }
     fn clean_up(&mut self) {}
+    /// What the module does itself on `trigger` when its table doesn't bind it.
+    /// Returns whether it handled it, its output is refreshed then.
+    fn handle(&mut self, _trigger: Trigger) -> bool { false }
 }
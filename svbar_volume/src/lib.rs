@@ -2,12 +2,21 @@ use std::sync::{Arc, Mutex};
 use libpulse_binding::{
     mainloop::standard::Mainloop,
     proplist::Proplist,
-    operation::State as OperationState,
+    operation::{Operation, State as OperationState},
     context::{Context, FlagSet, State},
-    def::Retval
+    def::Retval,
+    volume::{ChannelVolumes, Volume as PaVolume},
 };
 
 type VolumeResult = Result<u8, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    /// Percent of the average volume over the channels
+    pub level: u8,
+    pub muted: bool,
+}
+
 pub struct VolumeContext {
     mainloop: Mainloop,
     context: Context,
@@ -52,37 +61,101 @@ impl VolumeContext {
         })
     }
 
+    /// Volume, 0 if muted
     pub fn get(&mut self) -> VolumeResult {
-        let volume = Arc::new(Mutex::new(Err("Value wasn't changed".to_string()) as VolumeResult));
+        self.status().map(|volume| if volume.muted { 0 } else { volume.level })
+    }
+
+    /// Of the default sink, the one the volume keys change
+    pub fn status(&mut self) -> Result<Volume, String> {
+        let sink = self.default_sink()?;
+        let volume = Arc::new(Mutex::new(Err("Value wasn't changed".to_string()) as Result<Volume, String>));
         let copy = volume.clone();
 
-        let op = self.context.introspect().get_sink_info_list(move |info| {
-            match info {
-                libpulse_binding::callbacks::ListResult::Item(sink) => {
-                    if sink.mute {
-                        *copy.lock().unwrap() = Ok(0);
-                    }
-                    else {
-                        let mut raw_volume = sink.volume.avg().print().trim().to_string();
-                        raw_volume.remove(raw_volume.len() - 1);
-
-                        let new_value = match raw_volume.parse::<u8>() {
-                            Ok(volume) => Ok(volume),
-                            Err(why) => Err(why.to_string())
-                        };
-
-                        *copy.lock().unwrap() = new_value;
-                    }
-                }
-                _ => {}
+        let op = self.context.introspect().get_sink_info_by_name(&sink, move |info| {
+            if let libpulse_binding::callbacks::ListResult::Item(sink) = info {
+                let mut raw_volume = sink.volume.avg().print().trim().to_string();
+                raw_volume.remove(raw_volume.len() - 1);
+
+                let new_value = match raw_volume.parse::<u8>() {
+                    Ok(level) => Ok(Volume { level, muted: sink.mute }),
+                    Err(why) => Err(why.to_string())
+                };
+
+                *copy.lock().unwrap() = new_value;
             }
         });
+        self.wait(op);
+
+        volume.lock().unwrap().clone()
+    }
+
+    /// Changes the default sink's volume by `percent`, without raising it past `max` percent
+    pub fn change_volume(&mut self, percent: i32, max: u32) -> Result<(), String> {
+        let sink = self.default_sink()?;
+        let mut volumes = self.sink_volumes(&sink)?;
+        let step = percent_volume(percent.unsigned_abs());
+
+        let changed = match percent >= 0 {
+            // already past the cap is left alone, raising it would lower it
+            true if volumes.max() >= percent_volume(max) => return Ok(()),
+            true => volumes.inc_clamp(step, percent_volume(max)),
+            false => volumes.decrease(step),
+        };
+        if changed.is_none() {
+            return Err(format!("Couldn't change the volume of `{sink}`"));
+        }
+
+        let op = self.context.introspect().set_sink_volume_by_name(&sink, &volumes, None);
+        self.wait(op);
+        Ok(())
+    }
+
+    pub fn set_muted(&mut self, muted: bool) -> Result<(), String> {
+        let sink = self.default_sink()?;
+        let op = self.context.introspect().set_sink_mute_by_name(&sink, muted, None);
+        self.wait(op);
+        Ok(())
+    }
 
+    /// Returns whether it's muted now
+    pub fn toggle_mute(&mut self) -> Result<bool, String> {
+        let muted = !self.status()?.muted;
+        self.set_muted(muted)?;
+        Ok(muted)
+    }
+
+    fn default_sink(&mut self) -> Result<String, String> {
+        let name = Arc::new(Mutex::new(None));
+        let copy = name.clone();
+
+        let op = self.context.introspect().get_server_info(move |info| {
+            *copy.lock().unwrap() = info.default_sink_name.as_ref().map(|name| name.to_string());
+        });
+        self.wait(op);
+
+        name.lock().unwrap().clone().ok_or_else(|| "There's no default sink".into())
+    }
+
+    fn sink_volumes(&mut self, sink: &str) -> Result<ChannelVolumes, String> {
+        let volumes = Arc::new(Mutex::new(None));
+        let copy = volumes.clone();
+
+        let op = self.context.introspect().get_sink_info_by_name(sink, move |info| {
+            if let libpulse_binding::callbacks::ListResult::Item(sink) = info {
+                *copy.lock().unwrap() = Some(sink.volume);
+            }
+        });
+        self.wait(op);
+
+        let volumes = *volumes.lock().unwrap();
+        volumes.ok_or_else(|| format!("Couldn't get the volume of `{sink}`"))
+    }
+
+    fn wait<F: ?Sized>(&mut self, op: Operation<F>) {
         while op.get_state() == OperationState::Running {
             self.mainloop.iterate(false);
         }
-
-        volume.lock().unwrap().clone()
     }
 
     pub fn exit(&mut self) {
@@ -91,6 +164,10 @@ impl VolumeContext {
     }
 }
 
+fn percent_volume(percent: u32) -> PaVolume {
+    PaVolume((PaVolume::NORMAL.0 as u64 * percent as u64 / 100) as u32)
+}
+
 #[test]
 fn get_volume() {
     let mut context = VolumeContext::new().unwrap();